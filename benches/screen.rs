@@ -0,0 +1,68 @@
+// compares composing the whole frame against composing only the dirty region,
+// with a sprite bouncing around a large screen like most animated roms do
+//
+// cargo bench --bench screen
+
+use auxn::devices::ScreenDevice;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 768;
+const FRAMES: usize = 600;
+
+const SPRITE: [u8; 8] = [0x3c, 0x7e, 0xff, 0xff, 0xff, 0xff, 0x7e, 0x3c];
+
+// move the sprite one step, erasing it from its previous position
+fn animate(screen: &mut ScreenDevice, frame: usize) {
+    let x = (frame * 3 % (WIDTH as usize - 8)) as u16;
+    let y = (frame * 2 % (HEIGHT as usize - 8)) as u16;
+
+    if frame > 0 {
+        let px = ((frame - 1) * 3 % (WIDTH as usize - 8)) as u16;
+        let py = ((frame - 1) * 2 % (HEIGHT as usize - 8)) as u16;
+        screen.screen_blit(0x40, px, py, &SPRITE, 0x0, 0, 0, 0, 0);
+    }
+
+    screen.screen_blit(0x40, x, y, &SPRITE, 0x1, 0, 0, 0, 0);
+}
+
+fn full() -> Duration {
+    let mut screen = ScreenDevice::new(WIDTH, HEIGHT);
+    let start = Instant::now();
+
+    for frame in 0..FRAMES {
+        animate(&mut screen, frame);
+
+        screen.dirty = None;
+        black_box(screen.compose());
+    }
+
+    return start.elapsed();
+}
+
+fn dirty() -> Duration {
+    let mut screen = ScreenDevice::new(WIDTH, HEIGHT);
+    let start = Instant::now();
+
+    for frame in 0..FRAMES {
+        animate(&mut screen, frame);
+
+        if let Some(region) = screen.dirty.take() {
+            black_box(screen.compose_region(region));
+        }
+    }
+
+    return start.elapsed();
+}
+
+fn main() {
+    let full = full();
+    let dirty = dirty();
+
+    println!("{} frames at {}x{}", FRAMES, WIDTH, HEIGHT);
+    println!("full frame:   {:>10.3?} ({:.3?} per frame)", full, full / FRAMES as u32);
+    println!("dirty region: {:>10.3?} ({:.3?} per frame)", dirty, dirty / FRAMES as u32);
+    println!("speedup:      {:>9.1}x", full.as_secs_f64() / dirty.as_secs_f64());
+}
@@ -0,0 +1,504 @@
+// uxntal assembler, producing the same roms and symbol files as uxnasm
+//
+// runes:
+//   |  absolute padding      $  relative padding
+//   @  label                 &  sublabel
+//   #  literal hex           "  raw string          '  raw char
+//   .  literal zero-page     ,  literal relative    ;  literal absolute
+//   -  raw zero-page         _  raw relative        :  raw absolute
+//   %  macro definition      ~  include
+//
+// ( comments ) and [ brackets ] are ignored
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::system::NAMES;
+
+// includes deeper than this are most likely including themselves
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// the result of assembling a program
+pub struct Assembly {
+    // program bytes, starting at 0x100
+    pub rom: Vec<u8>,
+    // every label with its address, in the order they were defined
+    pub symbols: Vec<(u16, String)>,
+}
+
+impl Assembly {
+    // contents of the .sym file: a 16 bit address followed by a null terminated name
+    pub fn sym(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        for (addr, name) in &self.symbols {
+            out.push((addr >> 8) as u8);
+            out.push(*addr as u8);
+            out.extend_from_slice(name.as_bytes());
+            out.push(0);
+        }
+
+        return out;
+    }
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    file: Rc<PathBuf>,
+    line: usize,
+}
+
+impl Token {
+    fn error(&self, message: &str) -> AsmError {
+        AsmError {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            message: format!("{}: {}", message, self.text),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Reference {
+    // . and -
+    ZeroPage,
+    // , and _
+    Relative,
+    // ; and :
+    Absolute,
+}
+
+struct Unresolved {
+    kind: Reference,
+    // name of the label, already scoped
+    name: String,
+    // where the address has to be written
+    addr: usize,
+    // where the relative offset is taken from
+    origin: usize,
+    token: Token,
+}
+
+struct Assembler {
+    data: Vec<u8>,
+    ptr: usize,
+    length: usize,
+
+    scope: String,
+    labels: HashMap<String, u16>,
+    symbols: Vec<(u16, String)>,
+    macros: HashMap<String, Vec<Token>>,
+    references: Vec<Unresolved>,
+}
+
+pub fn assemble_file(path: &Path) -> Result<Assembly, AsmError> {
+    let source = fs::read_to_string(path).map_err(|e| AsmError {
+        file: path.to_path_buf(),
+        line: 0,
+        message: e.to_string(),
+    })?;
+
+    return assemble(&source, path);
+}
+
+// path is only used to report errors and to find includes
+pub fn assemble(source: &str, path: &Path) -> Result<Assembly, AsmError> {
+    let mut tokens = Vec::new();
+    tokenize(source, Rc::new(path.to_path_buf()), &mut tokens, 0)?;
+
+    let mut asm = Assembler {
+        data: vec![0; 0x10000],
+        ptr: 0,
+        length: 0,
+
+        scope: String::new(),
+        labels: HashMap::new(),
+        symbols: Vec::new(),
+        macros: HashMap::new(),
+        references: Vec::new(),
+    };
+
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        if let Some(name) = token.text.strip_prefix('%') {
+            asm.define_macro(name, &token, &mut tokens)?;
+        } else {
+            asm.parse(&token, 0)?;
+        }
+    }
+
+    asm.resolve()?;
+
+    let rom = if asm.length > 0x100 { asm.data[0x100..asm.length].to_vec() } else { Vec::new() };
+
+    return Ok(Assembly {
+        rom,
+        symbols: asm.symbols,
+    });
+}
+
+// split the source into words, dropping comments and brackets, and pulling in includes
+fn tokenize(source: &str, file: Rc<PathBuf>, tokens: &mut Vec<Token>, depth: usize) -> Result<(), AsmError> {
+    let mut comment = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        for word in line.split_whitespace() {
+            let token = Token {
+                text: word.to_string(),
+                file: file.clone(),
+                line: i + 1,
+            };
+
+            if word.starts_with('(') {
+                comment += 1;
+            }
+
+            if comment > 0 {
+                if word.ends_with(')') {
+                    comment -= 1;
+                }
+                continue;
+            }
+
+            if word == "[" || word == "]" {
+                continue;
+            }
+
+            if let Some(name) = word.strip_prefix('~') {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(token.error("Includes nested too deep"));
+                }
+
+                let path = file.parent().unwrap_or(Path::new("")).join(name);
+                let source = fs::read_to_string(&path).map_err(|_| token.error("Could not include"))?;
+
+                tokenize(&source, Rc::new(path), tokens, depth + 1)?;
+                continue;
+            }
+
+            tokens.push(token);
+        }
+    }
+
+    if comment > 0 {
+        return Err(AsmError {
+            file: file.to_path_buf(),
+            line: source.lines().count(),
+            message: String::from("Unterminated comment"),
+        });
+    }
+
+    return Ok(());
+}
+
+fn opcode(word: &str) -> Option<u8> {
+    if word == "BRK" {
+        return Some(0x00);
+    }
+
+    if word.len() < 3 || !word.is_ascii() {
+        return None;
+    }
+
+    let (name, modes) = word.split_at(3);
+    let mut op = NAMES.iter().position(|o| *o == name)? as u8;
+
+    // LIT only exists in keep mode
+    if op == 0 {
+        op |= 0x80;
+    }
+
+    for mode in modes.chars() {
+        match mode {
+            '2' => op |= 0x20,
+            'r' => op |= 0x40,
+            'k' => op |= 0x80,
+            _ => return None,
+        }
+    }
+
+    return Some(op);
+}
+
+fn hex(word: &str) -> Option<u16> {
+    if (word.len() == 2 || word.len() == 4) && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        return u16::from_str_radix(word, 16).ok();
+    }
+
+    return None;
+}
+
+// any length of hex, used by the paddings
+fn number(word: &str) -> Option<usize> {
+    if word.is_empty() || !word.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    return usize::from_str_radix(word, 16).ok();
+}
+
+impl Assembler {
+    fn define_macro(&mut self, name: &str, token: &Token, tokens: &mut impl Iterator<Item = Token>) -> Result<(), AsmError> {
+        if name.is_empty() || hex(name).is_some() || opcode(name).is_some() {
+            return Err(token.error("Invalid macro name"));
+        }
+
+        if self.macros.contains_key(name) {
+            return Err(token.error("Duplicate macro"));
+        }
+
+        match tokens.next() {
+            Some(open) if open.text == "{" => {}
+            _ => return Err(token.error("Macro is missing its opening brace")),
+        }
+
+        let mut body = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(t) if t.text == "}" => break,
+                Some(t) if t.text.starts_with('%') => return Err(t.error("Macro defined inside a macro")),
+                Some(t) => body.push(t),
+                None => return Err(token.error("Macro is missing its closing brace")),
+            }
+        }
+
+        self.macros.insert(name.to_string(), body);
+
+        return Ok(());
+    }
+
+    fn write_byte(&mut self, token: &Token, b: u8) -> Result<(), AsmError> {
+        if self.ptr < 0x100 {
+            return Err(token.error("Writing in zero-page"));
+        }
+
+        if self.ptr > 0xffff {
+            return Err(token.error("Writing outside of memory"));
+        }
+
+        self.data[self.ptr] = b;
+        self.ptr = self.ptr.checked_add(1).ok_or_else(|| token.error("Writing outside of memory"))?;
+
+        // going back with | to fill a gap must not cut off what was written further up
+        self.length = self.length.max(self.ptr);
+
+        return Ok(());
+    }
+
+    fn write_short(&mut self, token: &Token, s: u16) -> Result<(), AsmError> {
+        self.write_byte(token, (s >> 8) as u8)?;
+        self.write_byte(token, s as u8)
+    }
+
+    // sublabels are relative to the last label
+    fn scoped(&self, name: &str) -> String {
+        match name.strip_prefix('&') {
+            Some(sub) => format!("{}/{}", self.scope, sub),
+            None => name.to_string(),
+        }
+    }
+
+    fn define_label(&mut self, token: &Token, name: String) -> Result<(), AsmError> {
+        if name.is_empty() || name.ends_with('/') || hex(&name).is_some() || opcode(&name).is_some() {
+            return Err(token.error("Invalid label name"));
+        }
+
+        if self.labels.contains_key(&name) || self.macros.contains_key(&name) {
+            return Err(token.error("Duplicate label"));
+        }
+
+        self.labels.insert(name.clone(), self.ptr as u16);
+        self.symbols.push((self.ptr as u16, name));
+
+        return Ok(());
+    }
+
+    fn reference(&mut self, token: &Token, kind: Reference, name: &str, origin: usize) {
+        self.references.push(Unresolved {
+            kind,
+            name: self.scoped(name),
+            addr: self.ptr,
+            origin,
+            token: token.clone(),
+        });
+    }
+
+    fn parse(&mut self, token: &Token, depth: usize) -> Result<(), AsmError> {
+        let word = token.text.as_str();
+        let (rune, rest) = word.split_at(word.chars().next().map_or(0, |c| c.len_utf8()));
+
+        match rune {
+            "|" => {
+                self.ptr = match number(rest) {
+                    Some(addr) => addr,
+                    None => match self.labels.get(&self.scoped(rest)) {
+                        Some(addr) => *addr as usize,
+                        None => return Err(token.error("Invalid padding")),
+                    },
+                };
+            }
+
+            "$" => {
+                self.ptr = number(rest)
+                    .and_then(|len| self.ptr.checked_add(len))
+                    .ok_or_else(|| token.error("Invalid padding"))?;
+            }
+
+            "@" => {
+                self.define_label(token, rest.to_string())?;
+                self.scope = rest.to_string();
+            }
+
+            "&" => {
+                let name = self.scoped(word);
+                self.define_label(token, name)?;
+            }
+
+            "#" => match hex(rest) {
+                Some(val) if rest.len() == 2 => {
+                    self.write_byte(token, 0x80)?;
+                    self.write_byte(token, val as u8)?;
+                }
+                Some(val) => {
+                    self.write_byte(token, 0xa0)?;
+                    self.write_short(token, val)?;
+                }
+                None => return Err(token.error("Invalid hexadecimal literal")),
+            },
+
+            "." => {
+                self.write_byte(token, 0x80)?;
+                self.reference(token, Reference::ZeroPage, rest, 0);
+                self.write_byte(token, 0xff)?;
+            }
+
+            "," => {
+                // relative to the instruction that follows the literal
+                let origin = self.ptr + 3;
+                self.write_byte(token, 0x80)?;
+                self.reference(token, Reference::Relative, rest, origin);
+                self.write_byte(token, 0xff)?;
+            }
+
+            ";" => {
+                self.write_byte(token, 0xa0)?;
+                self.reference(token, Reference::Absolute, rest, 0);
+                self.write_short(token, 0xffff)?;
+            }
+
+            "-" => {
+                self.reference(token, Reference::ZeroPage, rest, 0);
+                self.write_byte(token, 0xff)?;
+            }
+
+            "_" => {
+                let origin = self.ptr + 2;
+                self.reference(token, Reference::Relative, rest, origin);
+                self.write_byte(token, 0xff)?;
+            }
+
+            ":" | "=" => {
+                self.reference(token, Reference::Absolute, rest, 0);
+                self.write_short(token, 0xffff)?;
+            }
+
+            "'" => {
+                let c = rest.bytes().next().ok_or_else(|| token.error("Empty character"))?;
+                self.write_byte(token, c)?;
+            }
+
+            "\"" => {
+                for c in rest.bytes() {
+                    self.write_byte(token, c)?;
+                }
+            }
+
+            "%" => {
+                return Err(token.error("Macro defined inside a macro"));
+            }
+
+            "{" | "}" => {
+                return Err(token.error("Unexpected brace"));
+            }
+
+            _ => {
+                if let Some(op) = opcode(word) {
+                    self.write_byte(token, op)?;
+                } else if let Some(val) = hex(word) {
+                    if word.len() == 2 {
+                        self.write_byte(token, val as u8)?;
+                    } else {
+                        self.write_short(token, val)?;
+                    }
+                } else if let Some(body) = self.macros.get(word).cloned() {
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(token.error("Macros nested too deep"));
+                    }
+
+                    for item in &body {
+                        self.parse(item, depth + 1)?;
+                    }
+                } else {
+                    return Err(token.error("Unknown token"));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn resolve(&mut self) -> Result<(), AsmError> {
+        for r in &self.references {
+            let addr = match self.labels.get(&r.name) {
+                Some(addr) => *addr,
+                None => return Err(r.token.error("Unknown label")),
+            };
+
+            match r.kind {
+                Reference::ZeroPage => {
+                    if addr > 0xff {
+                        return Err(r.token.error("Address is not in zero-page"));
+                    }
+
+                    self.data[r.addr] = addr as u8;
+                }
+
+                Reference::Relative => {
+                    let offset = addr as isize - r.origin as isize;
+
+                    if offset < -128 || offset > 127 {
+                        return Err(r.token.error("Relative reference is too far"));
+                    }
+
+                    self.data[r.addr] = offset as i8 as u8;
+                }
+
+                Reference::Absolute => {
+                    self.data[r.addr] = (addr >> 8) as u8;
+                    self.data[r.addr + 1] = addr as u8;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
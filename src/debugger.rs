@@ -0,0 +1,300 @@
+// pausing, stepping and breakpoints
+//
+// the debugger gets asked before every instruction whether the vector should stop there,
+// a stopped vector keeps its pc on the debugger until it is resumed
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::uxn::UXN;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Step {
+    // only stop on breakpoints
+    Continue,
+    // stop on the next instruction
+    Into,
+    // stop once the routine called at the current instruction returns here
+    Over { until: usize, depth: u8 },
+    // stop once the return stack drops below this depth
+    Out { depth: u8 },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Access {
+    // loads and DEI
+    Read,
+    // stores and DEO
+    Write,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Target {
+    Ram(RangeInclusive<u16>),
+    Port(u8),
+}
+
+// stops right after an instruction touches the target
+#[derive(Clone, PartialEq)]
+pub struct Watchpoint {
+    pub target: Target,
+    pub access: Access,
+    // only when this value is read or written, a byte or a short depending on the instruction
+    pub value: Option<u16>,
+}
+
+impl Watchpoint {
+    fn matches(&self, target: &Target, short: bool, access: Access, val: u16) -> bool {
+        if self.access != access || self.value.map_or(false, |v| v != val) {
+            return false;
+        }
+
+        // a short touches the byte after it as well
+        let extra = if short { 1 } else { 0 };
+
+        match (&self.target, target) {
+            (Target::Ram(range), Target::Ram(at)) => {
+                let first = *at.start();
+                *range.start() <= first.wrapping_add(extra) && first <= *range.end()
+            }
+            (Target::Port(port), Target::Port(at)) => *port == *at || (short && *port == at.wrapping_add(1)),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.target, self.access) {
+            (Target::Ram(range), Access::Read) if range.start() == range.end() => write!(f, "read {:04x}", range.start())?,
+            (Target::Ram(range), Access::Write) if range.start() == range.end() => write!(f, "write {:04x}", range.start())?,
+            (Target::Ram(range), Access::Read) => write!(f, "read {:04x}-{:04x}", range.start(), range.end())?,
+            (Target::Ram(range), Access::Write) => write!(f, "write {:04x}-{:04x}", range.start(), range.end())?,
+            (Target::Port(port), Access::Read) => write!(f, "DEI {:02x}", port)?,
+            (Target::Port(port), Access::Write) => write!(f, "DEO {:02x}", port)?,
+        }
+
+        if let Some(val) = self.value {
+            write!(f, " = {:02x}", val)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    pub watchpoints: Vec<Watchpoint>,
+    pub mode: Step,
+
+    // where the paused vector resumes from
+    pub pc: Option<usize>,
+    // why it stopped, for the panel
+    pub reason: String,
+    // instruction that set off the last watchpoint
+    pub trigger: Option<u16>,
+    // a watchpoint went off, stop before the next instruction
+    trap: Option<String>,
+
+    // the instruction the vector resumes on was already stopped at
+    resuming: bool,
+
+    // a write to the system debug port stops the vm as well,
+    // only where there is someone to resume it
+    pub stop_on_inspect: bool,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            mode: Step::Continue,
+
+            pc: None,
+            reason: String::new(),
+            trigger: None,
+            trap: None,
+
+            resuming: false,
+
+            stop_on_inspect: false,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pc.is_some()
+    }
+
+    // nothing to check, the vm runs at full speed
+    pub fn is_idle(&self) -> bool {
+        self.mode == Step::Continue && self.breakpoints.is_empty() && self.watchpoints.is_empty()
+    }
+
+    pub fn toggle_breakpoint(&mut self, addr: u16) {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+        }
+    }
+
+    // called before running the instruction at pc, depth is the return stack pointer
+    pub fn should_break(&mut self, pc: usize, depth: u8) -> bool {
+        if let Some(reason) = self.trap.take() {
+            self.resuming = false;
+            return self.stop(pc, reason);
+        }
+
+        if self.resuming {
+            self.resuming = false;
+            return false;
+        }
+
+        let reason = if self.breakpoints.contains(&(pc as u16)) {
+            "breakpoint"
+        } else {
+            match self.mode {
+                Step::Continue => return false,
+                Step::Into => "step",
+                Step::Over { until, depth: d } if pc == until && depth <= d => "step over",
+                Step::Out { depth: d } if depth < d => "step out",
+                _ => return false,
+            }
+        };
+
+        return self.stop(pc, String::from(reason));
+    }
+
+    fn stop(&mut self, pc: usize, reason: String) -> bool {
+        self.reason = reason;
+        self.mode = Step::Continue;
+        self.pc = Some(pc);
+
+        return true;
+    }
+
+    // the vector reached its end while stepping, stop on the first instruction of the next one
+    pub fn vector_done(&mut self) {
+        self.resuming = false;
+        self.trap = None;
+
+        if self.mode != Step::Continue {
+            self.mode = Step::Into;
+        }
+    }
+
+    // called by the loads and stores, pc is the instruction doing it
+    pub fn check_ram(&mut self, access: Access, addr: u16, short: bool, val: u16, pc: usize) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+
+        self.check(Target::Ram(addr..=addr), access, short, val, pc);
+    }
+
+    // called by DEI and DEO
+    pub fn check_port(&mut self, access: Access, port: u8, short: bool, val: u16, pc: usize) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+
+        self.check(Target::Port(port), access, short, val, pc);
+    }
+
+    fn check(&mut self, target: Target, access: Access, short: bool, val: u16, pc: usize) {
+        // the first one to go off on an instruction is the one reported
+        if self.trap.is_some() || !self.watchpoints.iter().any(|w| w.matches(&target, short, access, val)) {
+            return;
+        }
+
+        let what = match (&target, access) {
+            (Target::Ram(at), Access::Read) => format!("read {:04x}", at.start()),
+            (Target::Ram(at), Access::Write) => format!("write {:04x}", at.start()),
+            (Target::Port(port), Access::Read) => format!("DEI {:02x}", port),
+            (Target::Port(port), Access::Write) => format!("DEO {:02x}", port),
+        };
+
+        let val = if short { format!("{:04x}", val) } else { format!("{:02x}", val) };
+
+        self.trap = Some(format!("{} = {} at {:04x}", what, val, pc));
+        self.trigger = Some(pc as u16);
+    }
+
+    fn resume(&mut self, mode: Step) {
+        self.mode = mode;
+        self.trigger = None;
+        self.resuming = self.pc.is_some();
+    }
+}
+
+// stop before the next instruction, which is the first one of the next vector
+// unless this comes from inside a running one
+pub fn debug_pause(uxn: &mut UXN, reason: &str) {
+    if !uxn.debugger.is_paused() {
+        uxn.debugger.mode = Step::Into;
+        uxn.debugger.trap = Some(String::from(reason));
+        uxn.debugger.reason = String::from("waiting for a vector");
+    }
+}
+
+pub fn debug_continue(uxn: &mut UXN) {
+    uxn.debugger.resume(Step::Continue);
+    uxn.debugger.reason.clear();
+    uxn.resume();
+}
+
+pub fn debug_step(uxn: &mut UXN) {
+    uxn.debugger.resume(Step::Into);
+    uxn.resume();
+}
+
+// runs over subroutine calls, anything else is a single step
+pub fn debug_step_over(uxn: &mut UXN) {
+    let mode = match uxn.debugger.pc {
+        // JSR, JSR2, JSRr.. but not the literals
+        Some(pc) if uxn.ram[pc] & 0x1f == 0x0e => Step::Over {
+            until: pc + 1,
+            depth: uxn.ram[uxn.rst + 0xff],
+        },
+        _ => Step::Into,
+    };
+
+    uxn.debugger.resume(mode);
+    uxn.resume();
+}
+
+pub fn debug_step_out(uxn: &mut UXN) {
+    let depth = uxn.ram[uxn.rst + 0xff];
+
+    uxn.debugger.resume(Step::Out { depth });
+    uxn.resume();
+}
+
+// hex bytes like "a0 01 0e" or "a0010e"
+pub fn parse_pattern(text: &str) -> Option<Vec<u8>> {
+    let digits: String = text.split_whitespace().collect();
+
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return None;
+    }
+
+    return (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect();
+}
+
+// every address where the pattern starts, up to limit of them
+pub fn search(mem: &[u8], pattern: &[u8], limit: usize) -> Vec<usize> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    return mem
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| *window == pattern)
+        .map(|(addr, _)| addr)
+        .take(limit)
+        .collect();
+}
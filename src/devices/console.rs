@@ -1,10 +1,32 @@
 use crate::uxn::UXN;
 
-pub struct ConsoleDevice {}
+use std::collections::VecDeque;
+
+// types reported on port 0x17 when input is delivered
+pub const CONSOLE_STD: u8 = 0x1;
+pub const CONSOLE_ARG: u8 = 0x2;
+pub const CONSOLE_EOA: u8 = 0x3;
+pub const CONSOLE_END: u8 = 0x4;
+
+#[derive(Clone)]
+pub struct ConsoleDevice {
+    // address of the vector
+    pub vector: usize,
+    // bytes and their type still to be delivered, held back while the debugger holds a vector
+    pub queue: VecDeque<(u8, u8)>,
+}
 
 impl ConsoleDevice {
     pub fn new() -> Self {
-        ConsoleDevice {}
+        ConsoleDevice {
+            vector: 0,
+            queue: VecDeque::new(),
+        }
+    }
+
+    // return the console vector
+    pub fn vector(&self) -> usize {
+        return self.vector;
     }
 }
 
@@ -13,14 +35,11 @@ pub fn console(uxn: &mut UXN, port: usize, val: u8) {
 
     match rel {
         0x0 | 0x1 => {
-            println!("Set Console Vector");
-        }
+            if rel == 0x1 {
+                let a = (uxn.ram[uxn.dev + port - 1] as i32) << 8;
+                let b = (uxn.ram[uxn.dev + port] as i32);
 
-        0x2 => {
-            if val != 0 {
-                uxn.wst = (val as usize) * 0x100;
-            } else {
-                uxn.wst = 0x10000;
+                uxn.console.vector = (a | b) as usize;
             }
         }
 
@@ -43,3 +62,40 @@ pub fn console(uxn: &mut UXN, port: usize, val: u8) {
         }
     }
 }
+
+pub fn console_input(uxn: &mut UXN, c: u8, kind: u8) {
+    uxn.ram[uxn.dev + 0x12] = c;
+    uxn.ram[uxn.dev + 0x17] = kind;
+
+    let console_vector_addr = uxn.console.vector();
+    uxn.eval(console_vector_addr);
+}
+
+// deliver the program arguments the same way uxncli does,
+// one byte at a time, each argument terminated by a newline
+pub fn console_args(uxn: &mut UXN, args: &[String]) {
+    for (i, arg) in args.iter().enumerate() {
+        for c in arg.bytes() {
+            uxn.console.queue.push_back((c, CONSOLE_ARG));
+        }
+
+        if i == args.len() - 1 {
+            uxn.console.queue.push_back((b'\n', CONSOLE_END));
+        } else {
+            uxn.console.queue.push_back((b'\n', CONSOLE_EOA));
+        }
+    }
+
+    console_flush(uxn);
+}
+
+// hand over the queued bytes until there are none left or the debugger holds a vector,
+// the rest goes once that vector is done
+pub fn console_flush(uxn: &mut UXN) {
+    while !uxn.debugger.is_paused() {
+        match uxn.console.queue.pop_front() {
+            Some((c, kind)) => console_input(uxn, c, kind),
+            None => return,
+        }
+    }
+}
@@ -8,4 +8,10 @@ mod system;
 pub use system::*;
 
 mod mouse;
-pub use mouse::*;
\ No newline at end of file
+pub use mouse::*;
+
+mod screenshot;
+pub use screenshot::*;
+
+mod recorder;
+pub use recorder::*;
@@ -1,5 +1,6 @@
 use crate::uxn::UXN;
 
+#[derive(Clone)]
 pub struct MouseDevice {
     // address of the vector
     pub vector: usize,
@@ -48,7 +49,7 @@ pub fn mouse_pos(uxn: &mut UXN, screen_x: f32, screen_y: f32) {
 pub fn mouse_down(uxn: &mut UXN) {
     println!("mouse down!");
 
-    uxn.ram[uxn.dev + 0x96] =
+    uxn.ram[uxn.dev + 0x96] = 1;
 
     let mouse_vector_addr = uxn.mouse.vector();
     uxn.eval(mouse_vector_addr);
@@ -56,6 +57,9 @@ pub fn mouse_down(uxn: &mut UXN) {
 
 pub fn mouse_up(uxn: &mut UXN) {
     println!("mouse up!");
+
+    uxn.ram[uxn.dev + 0x96] = 0;
+
     let mouse_vector_addr = uxn.mouse.vector();
     uxn.eval(mouse_vector_addr);
 }
\ No newline at end of file
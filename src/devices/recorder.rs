@@ -0,0 +1,168 @@
+use crate::devices::ScreenDevice;
+
+use nih_plug_egui::egui::Color32;
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+// the screen vector runs at 60hz, gif delays are in hundredths of a second
+const TICKS_PER_SECOND: u32 = 60;
+
+// a frame waiting for the next one, so we know how long it stays on screen
+struct Pending {
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+    palette: [Color32; 4],
+    ticks: u32,
+}
+
+// records the screen as an animated gif, using the uxn palette
+// so every frame is just a few bits per pixel
+pub struct Recorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+
+    // size of the gif, frames bigger than this get cropped
+    width: u16,
+    height: u16,
+
+    palette: [Color32; 4],
+    pending: Option<Pending>,
+
+    // leftover of the conversion from ticks to hundredths
+    carry: u32,
+
+    pub frames: usize,
+}
+
+impl ScreenDevice {
+    // both layers mixed as palette indices, the way they are presented
+    pub fn indexed(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.fg.len());
+
+        for i in 0..self.fg.len() {
+            pixels.push(if self.fg[i] != 0 { self.fg[i] } else { self.bg[i] });
+        }
+
+        return pixels;
+    }
+
+    pub fn palette(&self) -> [Color32; 4] {
+        return [self.color0, self.color1, self.color2, self.color3];
+    }
+}
+
+impl Recorder {
+    pub fn start(path: &Path, screen: &ScreenDevice) -> io::Result<Self> {
+        let width = screen.width.min(u16::MAX as u32) as u16;
+        let height = screen.height.min(u16::MAX as u32) as u16;
+        let palette = screen.palette();
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file, width, height, &rgb(&palette)).map_err(encoding)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(encoding)?;
+
+        let mut recorder = Recorder {
+            encoder,
+
+            width,
+            height,
+
+            palette,
+            pending: None,
+
+            carry: 0,
+
+            frames: 0,
+        };
+
+        // whatever is on screen right now is the first frame
+        recorder.capture(screen)?;
+
+        return Ok(recorder);
+    }
+
+    // called every time the screen vector fires
+    pub fn tick(&mut self) {
+        if let Some(pending) = &mut self.pending {
+            pending.ticks += 1;
+        }
+    }
+
+    // called every time the screen is presented
+    pub fn capture(&mut self, screen: &ScreenDevice) -> io::Result<()> {
+        let width = (screen.width as u16).min(self.width);
+        let height = (screen.height as u16).min(self.height);
+
+        let indexed = screen.indexed();
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height as usize {
+            let row = y * screen.width as usize;
+            pixels.extend_from_slice(&indexed[row..row + width as usize]);
+        }
+
+        let frame = Pending {
+            width,
+            height,
+            pixels,
+            palette: screen.palette(),
+            ticks: 0,
+        };
+
+        if let Some(previous) = self.pending.replace(frame) {
+            self.write(previous)?;
+        }
+
+        return Ok(());
+    }
+
+    pub fn stop(mut self) -> io::Result<()> {
+        if let Some(mut last) = self.pending.take() {
+            // give the last frame at least a tick on screen
+            last.ticks = last.ticks.max(1);
+            self.write(last)?;
+        }
+
+        return Ok(());
+    }
+
+    fn write(&mut self, pending: Pending) -> io::Result<()> {
+        // a frame that did not last a tick is replaced by the next one
+        if pending.ticks == 0 {
+            return Ok(());
+        }
+
+        let hundredths = pending.ticks * 100 + self.carry;
+        self.carry = hundredths % TICKS_PER_SECOND;
+
+        let mut frame = gif::Frame::default();
+        frame.width = pending.width;
+        frame.height = pending.height;
+        frame.buffer = Cow::Owned(pending.pixels);
+        frame.delay = (hundredths / TICKS_PER_SECOND).min(u16::MAX as u32) as u16;
+
+        // palette changes mid recording get a palette of their own
+        if pending.palette != self.palette {
+            frame.palette = Some(rgb(&pending.palette));
+        }
+
+        self.encoder.write_frame(&frame).map_err(encoding)?;
+        self.frames += 1;
+
+        return Ok(());
+    }
+}
+
+fn rgb(palette: &[Color32; 4]) -> Vec<u8> {
+    palette.iter().flat_map(|c| [c.r(), c.g(), c.b()]).collect()
+}
+
+fn encoding(e: gif::EncodingError) -> io::Error {
+    match e {
+        gif::EncodingError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::Other, e),
+    }
+}
@@ -1,5 +1,5 @@
 use crate::uxn::UXN;
-use nih_plug_egui::egui::{Color32, ColorImage, Context, TextureHandle};
+use nih_plug_egui::egui::{Color32, ColorImage, Context, TextureFilter, TextureHandle, Vec2};
 
 static blending: [[u8; 16]; 5] = [
     [0,0,0,0,1,0,1,1,2,2,0,2,3,3,3,0],
@@ -9,14 +9,25 @@ static blending: [[u8; 16]; 5] = [
     [1,1,1,1,1,0,1,1,1,1,0,1,1,1,1,0],
 ];
 
+// how big the screen is shown on the editor
+#[derive(Clone, Copy, PartialEq)]
+pub enum Zoom {
+    // integer multiple of the uxn resolution
+    Scale(u32),
+    // as big as it fits, keeping the aspect ratio, it only ever scales up
+    Fit,
+}
+
+#[derive(Clone)]
 pub struct ScreenDevice {
     // width and height of the device
     pub width: u32,
     pub height: u32,
 
-    // both background and foreground buffers
-    pub fg: ColorImage,
-    pub bg: ColorImage,
+    // both background and foreground buffers,
+    // they hold palette indices, 0 on the foreground being transparent
+    pub fg: Vec<u8>,
+    pub bg: Vec<u8>,
 
     // the actual texture stored on the GPU
     pub display: Option<TextureHandle>,
@@ -34,6 +45,15 @@ pub struct ScreenDevice {
     // this boolean is true when we need to update
     pub redraw: bool,
 
+    // region touched since the last upload, as x1, y1, x2, y2 (exclusive)
+    pub dirty: Option<[usize; 4]>,
+
+    // presentation settings, they do not change what the rom sees
+    pub zoom: Zoom,
+    pub crt: bool,
+    // editor pixels every uxn pixel takes, as a whole number, the crt look is built for it
+    pub whole_zoom: usize,
+
     // system colors
     pub color0: Color32,
     pub color1: Color32,
@@ -51,15 +71,21 @@ impl ScreenDevice {
             x: 0,
             y: 0,
 
-            fg: ColorImage::new([w as usize, h as usize], Color32::TRANSPARENT),
-            bg: ColorImage::new([w as usize, h as usize], Color32::TRANSPARENT),
+            fg: vec![0; (w * h) as usize],
+            bg: vec![0; (w * h) as usize],
             
             display: None::<TextureHandle>,
 
             vector: 0,
             addr: 0,
 
-            redraw: false,
+            // the first frame has to be presented even if nothing was drawn
+            redraw: true,
+            dirty: Some([0, 0, w as usize, h as usize]),
+
+            zoom: Zoom::Scale(1),
+            crt: false,
+            whole_zoom: 1,
 
             color0: Color32::BLACK,
             color1: Color32::DARK_GRAY,
@@ -78,19 +104,109 @@ impl ScreenDevice {
         }
     }
 
-    // load the buffer to video memory
-    pub fn generate(&mut self, ctx: &Context) {
-    	let mut buffer = self.bg.clone();
+    // grow the dirty region so it covers the pixel
+    pub fn touch(&mut self, x: usize, y: usize) {
+        self.dirty = match self.dirty {
+            Some([x1, y1, x2, y2]) => Some([x1.min(x), y1.min(y), x2.max(x + 1), y2.max(y + 1)]),
+            None => Some([x, y, x + 1, y + 1]),
+        };
+
+        self.redraw = true;
+    }
 
-        // "mix" both buffers into one, but only temporarly
-    	for (i, p) in self.fg.pixels.iter().enumerate() {
-    		if *p != Color32::TRANSPARENT {
-    			buffer.pixels[i] = *p;
-    		}
-    	}
+    // the whole screen has to be uploaded again, e.g. after a palette change
+    pub fn touch_all(&mut self) {
+        self.dirty = Some([0, 0, self.width as usize, self.height as usize]);
+        self.redraw = true;
+    }
 
-        // upload that buffer as a texture to the GPU
-        self.display = Some(ctx.load_texture("buffer", buffer, Default::default()));
+    // "mix" both buffers into one, resolving the palette on the way
+    pub fn compose(&self) -> ColorImage {
+        return self.compose_region([0, 0, self.width as usize, self.height as usize]);
+    }
+
+    // same as compose, but only for the x1, y1, x2, y2 region
+    pub fn compose_region(&self, region: [usize; 4]) -> ColorImage {
+        let [x1, y1, x2, y2] = region;
+        let mut buffer = ColorImage::new([x2 - x1, y2 - y1], self.color0);
+
+        let mut p = 0;
+        for y in y1..y2 {
+            let row = y * (self.width as usize);
+
+            for i in row + x1..row + x2 {
+                let index = if self.fg[i] != 0 { self.fg[i] } else { self.bg[i] };
+                buffer.pixels[p] = self.get_color(index);
+                p += 1;
+            }
+        }
+
+        return buffer;
+    }
+
+    // texture pixels every uxn pixel takes, at 1x there is no room for the crt look
+    fn texture_scale(&self) -> usize {
+        if self.crt && self.whole_zoom > 1 {
+            return self.whole_zoom;
+        } else {
+            return 1;
+        }
+    }
+
+    // the region as it should look on the texture
+    fn present(&self, region: [usize; 4]) -> ColorImage {
+        let buffer = self.compose_region(region);
+        let scale = self.texture_scale();
+
+        if scale > 1 {
+            return scanlines(&buffer, scale);
+        } else {
+            return buffer;
+        }
+    }
+
+    // size in pixels the screen takes on the editor, and the zoom the texture gets built for
+    pub fn display_size(&mut self, available: Vec2) -> Vec2 {
+        let native = Vec2::new(self.width as f32, self.height as f32);
+        let fit = (available.x / native.x).min(available.y / native.y).max(1.0);
+
+        self.whole_zoom = match self.zoom {
+            Zoom::Scale(k) => k as usize,
+            Zoom::Fit => fit as usize,
+        };
+
+        match self.zoom {
+            Zoom::Scale(k) => native * k as f32,
+            // scanlines only stay even on a whole multiple
+            Zoom::Fit if self.crt => native * self.whole_zoom as f32,
+            Zoom::Fit => native * fit,
+        }
+    }
+
+    // load the buffer to video memory
+    pub fn generate(&mut self, ctx: &Context) {
+        let dirty = self.dirty.take();
+        let scale = self.texture_scale();
+        let size = [self.width as usize * scale, self.height as usize * scale];
+
+        let fits = match &self.display {
+            Some(texture) => texture.size() == size,
+            None => false,
+        };
+
+        if !fits {
+            // first frame or a resize, upload the whole buffer as a new texture
+            let buffer = self.present([0, 0, self.width as usize, self.height as usize]);
+            self.display = Some(ctx.load_texture("buffer", buffer, TextureFilter::Nearest));
+        } else if let Some(region) = dirty {
+            // only the part that changed travels to the GPU
+            let buffer = self.present(region);
+            let [x1, y1, ..] = region;
+
+            if let Some(texture) = &mut self.display {
+                texture.set_partial([x1 * scale, y1 * scale], buffer, TextureFilter::Nearest);
+            }
+        }
     }
 
     pub fn screen_blit(&mut self, layer: u8, x: u16, y: u16, sprite: &[u8], color: usize, flipx: u8, flipy: u8, twobpp: u8, opaque: u8) {
@@ -131,7 +247,7 @@ impl ScreenDevice {
                         }
                     };
 
-                    let pcolor = self.get_color(blending[ch as usize][color]);
+                    let pcolor = blending[ch as usize][color];
                     self.screen_write(nx as usize, ny as usize, pcolor, layer);
                 }
 
@@ -143,29 +259,25 @@ impl ScreenDevice {
         }
     }
 
-    pub fn screen_write(&mut self, x: usize, y: usize, mut color: Color32, layer: u8) {
+    pub fn screen_write(&mut self, x: usize, y: usize, color: u8, layer: u8) {
         // check that the coordiantes are actually aplicable to our screen
         // if not, we simply ignore them, this is a default behaviour
         if x < (self.width as usize) {
         	if y < (self.height as usize) {
+                let i = x + y * (self.width as usize);
 
         		 // write to the foreground buffer
         		if layer != 0x00 {
-                    // simulate alpha blending
-                    if color == self.color0 {
-                        color = Color32::TRANSPARENT;
-                    }
-
-        			if color != self.fg[(x, y)] {
-		            	self.fg[(x, y)] = color;
-		            	self.redraw = true;
+        			if color != self.fg[i] {
+		            	self.fg[i] = color;
+		            	self.touch(x, y);
         			}
 
         		 // write to the background buffer
         		} else {
-        			if color != self.bg[(x, y)] {
-		            	self.bg[(x, y)] = color;
-		            	self.redraw = true;
+        			if color != self.bg[i] {
+		            	self.bg[i] = color;
+		            	self.touch(x, y);
         			}
         		}
 
@@ -173,9 +285,38 @@ impl ScreenDevice {
         }
     }
 
-    pub fn resize(&mut self) {
-        self.fg = ColorImage::new([self.width as usize, self.height as usize], Color32::TRANSPARENT);
-        self.bg = ColorImage::new([self.width as usize, self.height as usize], Color32::TRANSPARENT);
+    // fill the rectangle going from x1,y1 up to (but not including) x2,y2
+    pub fn screen_fill(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, color: u8, layer: u8) {
+        for y in y1..y2.min(self.height as usize) {
+            for x in x1..x2.min(self.width as usize) {
+                self.screen_write(x, y, color, layer);
+            }
+        }
+    }
+
+    // keep whatever was drawn on the region both sizes have in common
+    pub fn resize(&mut self, w: u32, h: u32) {
+        let mut fg = vec![0; (w * h) as usize];
+        let mut bg = vec![0; (w * h) as usize];
+
+        let cols = w.min(self.width) as usize;
+        let rows = h.min(self.height) as usize;
+
+        for y in 0..rows {
+            let src = y * (self.width as usize);
+            let dst = y * (w as usize);
+
+            fg[dst..dst + cols].copy_from_slice(&self.fg[src..src + cols]);
+            bg[dst..dst + cols].copy_from_slice(&self.bg[src..src + cols]);
+        }
+
+        self.width = w;
+        self.height = h;
+
+        self.fg = fg;
+        self.bg = bg;
+
+        self.touch_all();
     }
 
     // return the screen vector
@@ -184,6 +325,47 @@ impl ScreenDevice {
     }
 }
 
+// blow the image up to the zoom it is shown at and darken the edges of every pixel,
+// like the scanlines and the phosphor mask of a crt
+fn scanlines(image: &ColorImage, scale: usize) -> ColorImage {
+    let [w, h] = image.size;
+    let mut out = ColorImage::new([w * scale, h * scale], Color32::BLACK);
+
+    for y in 0..h * scale {
+        for x in 0..w * scale {
+            let c = image.pixels[(y / scale) * w + x / scale];
+
+            let shade: u16 = match (x % scale == scale - 1, y % scale == scale - 1) {
+                (_, true) => 128,
+                (true, false) => 192,
+                (false, false) => 256,
+            };
+
+            out.pixels[y * w * scale + x] = Color32::from_rgb(
+                ((c.r() as u16 * shade) >> 8) as u8,
+                ((c.g() as u16 * shade) >> 8) as u8,
+                ((c.b() as u16 * shade) >> 8) as u8,
+            );
+        }
+    }
+
+    return out;
+}
+
+pub fn screen_read(uxn: &UXN, port: usize) -> u8 {
+    let rel = port & 0x0F;
+
+    match rel {
+        // report the current size of the screen
+        0x2 => (uxn.screen.width >> 8) as u8,
+        0x3 => uxn.screen.width as u8,
+        0x4 => (uxn.screen.height >> 8) as u8,
+        0x5 => uxn.screen.height as u8,
+
+        _ => uxn.dev_get(port),
+    }
+}
+
 pub fn screen(uxn: &mut UXN, port: usize, val: u8) {
     let rel = port & 0x0F;
     let section = port & 0xF0;
@@ -209,9 +391,8 @@ pub fn screen(uxn: &mut UXN, port: usize, val: u8) {
 		            (a+b) as usize
 		        };
 
-		        uxn.screen.width = w as u32;
-
-		        uxn.screen.resize();
+		        let h = uxn.screen.height;
+		        uxn.screen.resize(w as u32, h);
         	}
         }
 
@@ -225,9 +406,8 @@ pub fn screen(uxn: &mut UXN, port: usize, val: u8) {
 		            (a+b) as usize
 		        };
 
-		        uxn.screen.height = h as u32;
-
-		        uxn.screen.resize();
+		        let w = uxn.screen.width;
+		        uxn.screen.resize(w, h as u32);
         	}
         }
 
@@ -272,9 +452,22 @@ pub fn screen(uxn: &mut UXN, port: usize, val: u8) {
         0xe => {
             let x = uxn.screen.x as usize;
             let y = uxn.screen.y as usize;
-            let color = uxn.screen.get_color(uxn.ram[uxn.dev + port] & 0x3);
+            let color = uxn.ram[uxn.dev + port] & 0x3;
             let layer = uxn.ram[uxn.dev + port] & 0x40;
 
+            // fill mode, paint everything from x,y to the corner
+            // of the screen the flip bits point to
+            if (uxn.dev_get(port) & 0x80) != 0 {
+                let width = uxn.screen.width as usize;
+                let height = uxn.screen.height as usize;
+
+                let (x1, x2) = if (uxn.dev_get(port) & 0x10) != 0 { (0, x) } else { (x, width) };
+                let (y1, y2) = if (uxn.dev_get(port) & 0x20) != 0 { (0, y) } else { (y, height) };
+
+                uxn.screen.screen_fill(x1, y1, x2, y2, color, layer);
+                return;
+            }
+
             uxn.screen.screen_write(x, y, color, layer);
 
             if (uxn.dev_get(section + 0x6) & 0x01) != 0 {
@@ -363,3 +556,11 @@ pub fn screen(uxn: &mut UXN, port: usize, val: u8) {
         }
     }
 }
+
+// resize coming from the host, the rom gets a chance to redraw right away
+pub fn screen_resize(uxn: &mut UXN, w: u32, h: u32) {
+    uxn.screen.resize(w, h);
+
+    let screen_vector_addr = uxn.screen.vector();
+    uxn.eval(screen_vector_addr);
+}
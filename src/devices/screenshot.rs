@@ -0,0 +1,78 @@
+use crate::devices::ScreenDevice;
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+impl ScreenDevice {
+    // what is on screen as rgba bytes, without going through egui
+    pub fn rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.fg.len() * 4);
+
+        for index in self.indexed() {
+            let c = self.get_color(index);
+            rgba.extend_from_slice(&[c.r(), c.g(), c.b(), 0xff]);
+        }
+
+        return rgba;
+    }
+
+    // save what is on screen, every uxn pixel becoming a scale x scale block
+    pub fn screenshot(&self, path: &Path, scale: u32) -> io::Result<()> {
+        let image = self.compose();
+        let scale = scale.max(1) as usize;
+
+        let [w, h] = image.size;
+        let mut rgba = Vec::with_capacity(w * h * scale * scale * 4);
+
+        for y in 0..h * scale {
+            for x in 0..w * scale {
+                let c = image.pixels[(y / scale) * w + x / scale];
+                rgba.extend_from_slice(&[c.r(), c.g(), c.b(), 0xff]);
+            }
+        }
+
+        return write_png(path, (w * scale) as u32, (h * scale) as u32, &rgba);
+    }
+
+    // save each layer on its own file next to path, as <name>-fg.png and <name>-bg.png,
+    // the transparent pixels of the foreground stay transparent
+    pub fn save_layers(&self, path: &Path) -> io::Result<()> {
+        let mut fg = Vec::with_capacity(self.fg.len() * 4);
+        let mut bg = Vec::with_capacity(self.bg.len() * 4);
+
+        for i in 0..self.fg.len() {
+            let c = self.get_color(self.fg[i]);
+            let alpha = if self.fg[i] != 0 { 0xff } else { 0x00 };
+            fg.extend_from_slice(&[c.r(), c.g(), c.b(), alpha]);
+
+            let c = self.get_color(self.bg[i]);
+            bg.extend_from_slice(&[c.r(), c.g(), c.b(), 0xff]);
+        }
+
+        write_png(&layer_path(path, "fg"), self.width, self.height, &fg)?;
+        write_png(&layer_path(path, "bg"), self.width, self.height, &bg)?;
+
+        return Ok(());
+    }
+}
+
+// screenshot.png -> screenshot-fg.png
+fn layer_path(path: &Path, layer: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    return path.with_file_name(format!("{}-{}.png", stem, layer));
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+
+    return Ok(());
+}
@@ -1,13 +1,56 @@
+use crate::debugger::debug_pause;
+use crate::fault::Fault;
 use crate::uxn::UXN;
 use nih_plug_egui::egui::Color32;
 
+use std::fmt::Write;
+
+// amount of 64kb memory banks, the first one being the main memory
+pub const BANKS: usize = 0x10;
+
+// name and description a rom can point the system device to
+#[derive(Clone)]
+pub struct Metadata {
+    pub name: String,
+    pub description: String,
+}
+
 // i know that "technically" the colors should be stored in this device,
 // but for convenience, they are better stored in the screen device
-pub struct SystemDevice {}
+#[derive(Clone)]
+pub struct SystemDevice {
+    // expansion memory, banks 1 and up, allocated the first time they are touched
+    pub banks: Vec<Vec<u8>>,
+
+    // exit code of the rom, once set the machine no longer runs
+    pub exit: Option<u8>,
+
+    // what stopped the machine, it no longer runs either
+    pub fault: Option<Fault>,
+
+    // metadata block read from the address given on port 0x06
+    pub meta: Option<Metadata>,
+}
 
 impl SystemDevice {
     pub fn new() -> Self {
-        SystemDevice {}
+        SystemDevice {
+            banks: Vec::new(),
+
+            exit: None,
+            fault: None,
+
+            meta: None,
+        }
+    }
+
+    // return the expansion bank, bank 0 lives in the uxn ram instead
+    pub fn bank(&mut self, bank: usize) -> &mut Vec<u8> {
+        while self.banks.len() < bank {
+            self.banks.push(vec![0; 0x10000]);
+        }
+
+        return &mut self.banks[bank - 1];
     }
 }
 
@@ -19,6 +62,36 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
             println!("Set System Vector");
         }
 
+        // run the command block pointed by the expansion port
+        0x2 | 0x3 => {
+            if rel == 0x3 {
+                let a = (uxn.ram[uxn.dev + port - 1] as i32) << 8;
+                let b = (uxn.ram[uxn.dev + port] as i32);
+
+                expansion(uxn, (a | b) as usize);
+            }
+        }
+
+        // set the depth of the working stack
+        0x4 => {
+            uxn.ram[uxn.wst + 0xff] = val;
+        }
+
+        // set the depth of the return stack
+        0x5 => {
+            uxn.ram[uxn.rst + 0xff] = val;
+        }
+
+        // register the metadata address
+        0x6 | 0x7 => {
+            if rel == 0x7 {
+                let a = (uxn.ram[uxn.dev + port - 1] as i32) << 8;
+                let b = (uxn.ram[uxn.dev + port] as i32);
+
+                uxn.system.meta = Some(metadata(uxn, (a | b) as usize));
+            }
+        }
+
         // Set the red spectrum color for color0 and color1
         0x8 => {
             let (c0_red, c1_red) = palette(uxn.dev_get(port));
@@ -28,6 +101,8 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c1 = uxn.screen.color1;
             uxn.screen.color1 = Color32::from_rgb(c1_red, c1.g(), c1.b());
+
+            uxn.screen.touch_all();
         }
 
         // Set the red spectrum color for color2 and color3
@@ -39,6 +114,8 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c3 = uxn.screen.color3;
             uxn.screen.color3 = Color32::from_rgb(c3_red, c3.g(), c3.b());
+
+            uxn.screen.touch_all();
         }
 
         // Set the green spectrum color for color0 and color1
@@ -50,6 +127,8 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c1 = uxn.screen.color1;
             uxn.screen.color1 = Color32::from_rgb(c1.r(), c1_green, c1.b());
+
+            uxn.screen.touch_all();
         }
 
         // Set the green spectrum color for color2 and color3
@@ -61,6 +140,8 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c3 = uxn.screen.color3;
             uxn.screen.color3 = Color32::from_rgb(c3.r(), c3_green, c3.b());
+
+            uxn.screen.touch_all();
         }
 
         // Set the blue spectrum color for color0 and color1
@@ -72,6 +153,8 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c1 = uxn.screen.color1;
             uxn.screen.color1 = Color32::from_rgb(c1.r(), c1.g(), c1_blue);
+
+            uxn.screen.touch_all();
         }
 
         // Set the blue spectrum color for color2 and color3
@@ -83,15 +166,28 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
 
             let c3 = uxn.screen.color3;
             uxn.screen.color3 = Color32::from_rgb(c3.r(), c3.g(), c3_blue);
+
+            uxn.screen.touch_all();
         }
 
+        // system inspect
         0xe => {
-            // system inspect
-            println!("system inspect - not implemented");
+            if val != 0 {
+                eprint!("{}", inspect(uxn));
+
+                // the debugger shows the same stacks right after this instruction
+                if uxn.debugger.stop_on_inspect {
+                    debug_pause(uxn, "system debug port");
+                }
+            }
         }
 
+        // halt the machine with an exit code
         0xf => {
-            println!("\nProgram Ended");
+            if val != 0 {
+                uxn.system.exit = Some(val & 0x7f);
+                uxn.halted = true;
+            }
         }
 
         _ => {
@@ -100,6 +196,128 @@ pub fn system(uxn: &mut UXN, port: usize, val: u8) {
     }
 }
 
+pub fn system_read(uxn: &UXN, port: usize) -> u8 {
+    let rel = port & 0x0F;
+
+    match rel {
+        // working stack pointer
+        0x4 => uxn.wst_get(0xff),
+
+        // return stack pointer
+        0x5 => uxn.rst_get(0xff),
+
+        _ => uxn.dev_get(port),
+    }
+}
+
+// the block starts with a version byte, followed by a null terminated text
+// with the name of the rom on the first line and the description on the rest
+fn metadata(uxn: &UXN, addr: usize) -> Metadata {
+    let mut text = Vec::new();
+    let mut i = addr + 1;
+
+    while i < 0x10000 && uxn.ram[i] != 0 {
+        text.push(uxn.ram[i]);
+        i += 1;
+    }
+
+    let text = String::from_utf8_lossy(&text);
+    let (name, description) = text.split_once('\n').unwrap_or((&text, ""));
+
+    Metadata {
+        name: name.trim().to_string(),
+        description: description.trim().to_string(),
+    }
+}
+
+// dump both stacks in the same format as uxnemu, followed by their pointers
+pub fn inspect(uxn: &UXN) -> String {
+    let mut out = String::new();
+
+    stack_print(&mut out, "wst", &uxn.ram[uxn.wst..uxn.wst + 0x100]);
+    stack_print(&mut out, "rst", &uxn.ram[uxn.rst..uxn.rst + 0x100]);
+
+    return out;
+}
+
+fn stack_print(out: &mut String, name: &str, stack: &[u8]) {
+    // the pointer lives in the last byte of the stack
+    let ptr = stack[0xff] as usize;
+
+    write!(out, "<{}>", name).unwrap();
+
+    for val in &stack[..ptr] {
+        write!(out, " {:02x}", val).unwrap();
+    }
+
+    if ptr == 0 {
+        out.push_str(" empty");
+    }
+
+    writeln!(out, " (ptr {:02x})", ptr).unwrap();
+}
+
+fn expansion(uxn: &mut UXN, addr: usize) {
+    let op = uxn.ram[addr];
+    let length = uxn.PEEK16(addr + 1) as usize;
+
+    match op {
+        // fill: length, bank, addr, value
+        0x00 => {
+            let bank = uxn.PEEK16(addr + 3) as usize;
+            let dst = uxn.PEEK16(addr + 5) as usize;
+            let value = uxn.ram[addr + 7];
+
+            for i in 0..length {
+                bank_poke(uxn, bank, dst + i, value);
+            }
+        }
+
+        // copy: length, src bank, src addr, dst bank, dst addr
+        // copying left walks forwards, copying right walks backwards,
+        // so overlapping ranges end up the way the rom expects
+        0x01 | 0x02 => {
+            let src_bank = uxn.PEEK16(addr + 3) as usize;
+            let src = uxn.PEEK16(addr + 5) as usize;
+            let dst_bank = uxn.PEEK16(addr + 7) as usize;
+            let dst = uxn.PEEK16(addr + 9) as usize;
+
+            for j in 0..length {
+                let i = if op == 0x01 { j } else { length - 1 - j };
+
+                let val = bank_peek(uxn, src_bank, src + i);
+                bank_poke(uxn, dst_bank, dst + i, val);
+            }
+        }
+
+        _ => {
+            println!("System - Unknown expansion command - {:x?}", op);
+        }
+    }
+}
+
+fn bank_peek(uxn: &mut UXN, bank: usize, addr: usize) -> u8 {
+    let bank = bank % BANKS;
+    let addr = addr & 0xffff;
+
+    if bank == 0 {
+        return uxn.ram[addr];
+    } else {
+        return uxn.system.bank(bank)[addr];
+    }
+}
+
+fn bank_poke(uxn: &mut UXN, bank: usize, addr: usize, val: u8) {
+    let bank = bank % BANKS;
+    let addr = addr & 0xffff;
+
+    if bank == 0 {
+        uxn.ram[addr] = val;
+    } else {
+        uxn.system.bank(bank)[addr] = val;
+    }
+}
+
 fn palette(mem_color: u8) -> (u8, u8) {
     let c1 = (mem_color >> (1 << 2)) & 0x0F;
     let c2 = (mem_color >> (0 << 2)) & 0x0F;
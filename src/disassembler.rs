@@ -0,0 +1,134 @@
+// turns uxn bytecode back into uxntal-ish text
+//
+// literals take their operand with them, and addresses that match a label
+// get it written next to them as a comment
+
+use std::ops::Range;
+
+use crate::symbols::SymbolTable;
+use crate::system::NAMES;
+
+pub struct Instruction {
+    pub addr: u16,
+    // opcode plus any literal operand
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+// the name without its mode suffixes, BRK takes the place of a LIT without keep mode
+pub fn name(op: u8) -> &'static str {
+    if op == 0x00 {
+        return "BRK";
+    }
+
+    return NAMES[(op & 0x1f) as usize];
+}
+
+pub fn mnemonic(op: u8) -> String {
+    let mut text = String::from(name(op));
+
+    if op == 0x00 {
+        return text;
+    }
+
+    if op & 0x20 != 0 {
+        text.push('2');
+    }
+
+    // LIT is always in keep mode, the k would just be noise
+    if op & 0x80 != 0 && op & 0x1f != 0 {
+        text.push('k');
+    }
+
+    if op & 0x40 != 0 {
+        text.push('r');
+    }
+
+    return text;
+}
+
+fn is_literal(op: u8) -> bool {
+    op != 0x00 && op & 0x1f == 0x00
+}
+
+// the relative jumps take a single byte offset from the next instruction
+fn is_relative_jump(op: u8) -> bool {
+    op & 0x20 == 0 && matches!(op & 0x1f, 0x0c | 0x0d | 0x0e)
+}
+
+fn annotate(text: &mut String, addr: u16, symbols: &SymbolTable) {
+    if let Some(name) = symbols.name(addr) {
+        text.push_str(&format!(" ( {} )", name));
+    }
+}
+
+// decode every instruction that starts inside the range,
+// the last literal can read past its end
+pub fn disassemble(mem: &[u8], range: Range<usize>, symbols: &SymbolTable) -> Vec<Instruction> {
+    let mut out = Vec::new();
+
+    let end = range.end.min(mem.len()).min(0x10000);
+    let mut addr = range.start;
+
+    while addr < end {
+        let op = mem[addr];
+        let mut len = 1;
+        let mut text = mnemonic(op);
+
+        if is_literal(op) {
+            let size = if op & 0x20 != 0 { 2 } else { 1 };
+            let operand = &mem[addr + 1..(addr + 1 + size).min(mem.len())];
+
+            if operand.len() == size {
+                len += size;
+
+                let val = if size == 2 { u16::from_be_bytes([operand[0], operand[1]]) } else { operand[0] as u16 };
+                let digits = size * 2;
+
+                // the # shorthand only exists for the working stack
+                text = if op & 0x40 != 0 {
+                    format!("{} {:02$x}", text, val, digits)
+                } else {
+                    format!("#{:01$x}", val, digits)
+                };
+
+                // small shorts are more often numbers than zero-page addresses
+                if size == 2 && val >= 0x100 {
+                    annotate(&mut text, val, symbols);
+                } else if mem.get(addr + 2).map_or(false, |next| is_relative_jump(*next)) {
+                    // a byte literal feeding a jump is an offset, show where it lands
+                    let target = (addr as u16).wrapping_add(3).wrapping_add(val as u8 as i8 as u16);
+                    annotate(&mut text, target, symbols);
+                }
+            }
+        }
+
+        out.push(Instruction {
+            addr: addr as u16,
+            bytes: mem[addr..addr + len].to_vec(),
+            text,
+        });
+
+        addr += len;
+    }
+
+    return out;
+}
+
+// one instruction per line, preceded by the labels that point at it
+pub fn listing(instructions: &[Instruction], symbols: &SymbolTable) -> String {
+    let mut out = String::new();
+
+    for instr in instructions {
+        for name in symbols.names(instr.addr) {
+            let rune = if name.contains('/') { '&' } else { '@' };
+            let short = name.rsplit('/').next().unwrap_or(name);
+            out.push_str(&format!("{}{}\n", rune, short));
+        }
+
+        let bytes: Vec<String> = instr.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        out.push_str(&format!("    {:04x}  {:<8}  {}\n", instr.addr, bytes.join(" "), instr.text));
+    }
+
+    return out;
+}
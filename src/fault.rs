@@ -0,0 +1,54 @@
+// errors a rom can run into, they stop the machine on the instruction that caused them
+// instead of taking the whole plugin down
+
+use std::fmt;
+
+use crate::symbols::SymbolTable;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stack {
+    Working,
+    Return,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    Underflow(Stack),
+    Overflow(Stack),
+    DivisionByZero,
+    UnknownInstruction(u8),
+}
+
+impl fmt::Display for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stack::Working => write!(f, "working stack"),
+            Stack::Return => write!(f, "return stack"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Underflow(stack) => write!(f, "{} underflow", stack),
+            Error::Overflow(stack) => write!(f, "{} overflow", stack),
+            Error::DivisionByZero => write!(f, "division by zero"),
+            Error::UnknownInstruction(op) => write!(f, "unknown instruction {:02x}", op),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fault {
+    pub error: Error,
+    // the instruction that went wrong
+    pub pc: u16,
+}
+
+impl Fault {
+    // one line, with the label of the instruction when there is a .sym
+    pub fn report(&self, symbols: &SymbolTable) -> String {
+        return format!("{} at {}", self.error, symbols.address(self.pc));
+    }
+}
@@ -0,0 +1,133 @@
+// running roms without the editor, for tests and scripts:
+// the screen is rendered to memory and compared against reference images
+//
+// set AUXN_BLESS=1 to (re)write the references from the current output
+
+use crate::devices::write_png;
+use crate::uxn::UXN;
+
+use std::env;
+use std::fmt::Write;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// how many mismatching pixels are listed on a failure
+const REPORTED: usize = 8;
+
+// boot the rom and fire the screen vector once per frame
+pub fn run(rom: Vec<u8>, width: u32, height: u32, frames: usize) -> UXN {
+    return run_on(UXN::new(width, height), rom, frames);
+}
+
+// same as run, on a vm that was already set up, e.g. to trace it
+pub fn run_on(mut uxn: UXN, rom: Vec<u8>, frames: usize) -> UXN {
+    uxn.load(rom);
+    uxn.eval(0x100);
+
+    for _ in 0..frames {
+        let screen_vector_addr = uxn.screen.vector();
+        uxn.eval(screen_vector_addr);
+    }
+
+    return uxn;
+}
+
+// compare what is on screen with the png at reference, on a mismatch the
+// actual output and a diff image are written next to it
+pub fn compare(uxn: &UXN, reference: &Path) -> Result<(), String> {
+    let (width, height) = (uxn.screen.width, uxn.screen.height);
+    let actual = uxn.screen.rgba();
+
+    if env::var_os("AUXN_BLESS").is_some() {
+        write_png(reference, width, height, &actual).map_err(|e| format!("{}: {}", reference.display(), e))?;
+        return Ok(());
+    }
+
+    let (expected_width, expected_height, expected) = read_png(reference)?;
+
+    if (expected_width, expected_height) != (width, height) {
+        write_png(&sibling(reference, "actual"), width, height, &actual).ok();
+
+        return Err(format!(
+            "{}: expected a {}x{} screen, got {}x{}",
+            reference.display(), expected_width, expected_height, width, height
+        ));
+    }
+
+    let mut report = String::new();
+    let mut diff = vec![0; actual.len()];
+    let mut count = 0;
+    let mut bounds = [usize::MAX, usize::MAX, 0, 0];
+
+    for (i, (a, e)) in actual.chunks(4).zip(expected.chunks(4)).enumerate() {
+        let (x, y) = (i % width as usize, i / width as usize);
+
+        if a == e {
+            // keep the matching pixels around, but faded
+            diff[i * 4..i * 4 + 4].copy_from_slice(&[a[0] / 4, a[1] / 4, a[2] / 4, 0xff]);
+            continue;
+        }
+
+        diff[i * 4..i * 4 + 4].copy_from_slice(&[0xff, 0x00, 0xff, 0xff]);
+
+        if count < REPORTED {
+            writeln!(
+                report,
+                "  ({}, {}): expected #{:02x}{:02x}{:02x}, got #{:02x}{:02x}{:02x}",
+                x, y, e[0], e[1], e[2], a[0], a[1], a[2]
+            ).unwrap();
+        }
+
+        count += 1;
+        bounds = [bounds[0].min(x), bounds[1].min(y), bounds[2].max(x), bounds[3].max(y)];
+    }
+
+    if count == 0 {
+        return Ok(());
+    }
+
+    let actual_path = sibling(reference, "actual");
+    let diff_path = sibling(reference, "diff");
+    write_png(&actual_path, width, height, &actual).ok();
+    write_png(&diff_path, width, height, &diff).ok();
+
+    let mut message = format!(
+        "{}: {} pixels differ, between ({}, {}) and ({}, {})\n",
+        reference.display(), count, bounds[0], bounds[1], bounds[2], bounds[3]
+    );
+    message.push_str(&report);
+
+    if count > REPORTED {
+        writeln!(message, "  ... and {} more", count - REPORTED).unwrap();
+    }
+
+    write!(message, "see {} and {}", actual_path.display(), diff_path.display()).unwrap();
+
+    return Err(message);
+}
+
+// reference.png -> reference.actual.png
+fn sibling(path: &Path, kind: &str) -> PathBuf {
+    return path.with_extension(format!("{}.png", kind));
+}
+
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("{}: {} (run with AUXN_BLESS=1 to create it)", path.display(), e);
+
+    let file = File::open(path).map_err(|e| fail(&e))?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND);
+
+    let mut reader = decoder.read_info().map_err(|e| fail(&e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| fail(&e))?;
+
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(fail(&"expected an 8 bit rgba image"));
+    }
+
+    buffer.truncate(info.buffer_size());
+
+    return Ok((info.width, info.height, buffer));
+}
@@ -8,24 +8,174 @@ use nih_plug_egui::egui::Widget;
 
 use egui_memory_editor::MemoryEditor;
 
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use std::{mem, thread, time};
-
-mod devices;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::{fs, mem, thread, time};
+
+pub mod assembler;
+pub mod debugger;
+pub mod devices;
+pub mod disassembler;
+pub mod fault;
+pub mod headless;
 mod operations;
+pub mod rewind;
+pub mod scheduler;
+pub mod symbols;
 mod system;
-mod uxn;
-
+pub mod trace;
+pub mod uxn;
+
+use devices::{Recorder, Zoom};
+use debugger::{Access, Target, Watchpoint};
+use rewind::Input;
+use scheduler::{Policy, Scheduler};
+use symbols::SymbolTable;
 use uxn::UXN;
 
 /// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
 const PEAK_METER_DECAY_MS: f64 = 150.0;
 
+/// Rate at which the screen vector fires, like on the varvara reference implementation.
+const SCREEN_RATE: u32 = 60;
+
+/// How many bytes of memory the disassembly window decodes at once.
+const DISASM_BYTES: usize = 0x200;
+
+/// How many instructions the debugger shows from the pc on.
+const DEBUGGER_LINES: usize = 8;
+
+/// How many matches the memory search lists.
+const SEARCH_RESULTS: usize = 64;
+
+/// How many instructions the trace ring buffer keeps.
+const TRACE_CAPACITY: usize = 0x1000;
+
+/// How many of the latest trace records the trace window shows.
+const TRACE_LINES: usize = 32;
+
+/// How many messages the console window keeps around.
+const CONSOLE_LINES: usize = 200;
+
+/// How often a watched source file gets checked for changes.
+const WATCH_INTERVAL_MS: u64 = 500;
+
 // 512 * 320
 
-const WIDTH: u32 = 64 * 8;
-const HEIGHT: u32 = 40 * 8;
+pub const WIDTH: u32 = 64 * 8;
+pub const HEIGHT: u32 = 40 * 8;
+
+// the thread firing the screen vector for the window that is open,
+// dropping it stops the thread and waits for it to finish
+struct ScreenThread {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for ScreenThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// settings of the capture window, kept between frames
+struct Capture {
+    path: String,
+    scale: u32,
+    // debug option, save each layer on its own file as well
+    layers: bool,
+    // result of the last capture
+    status: String,
+
+    gif_path: String,
+    recorder: Option<Recorder>,
+}
+
+// byte pattern search over memory
+struct Search {
+    pattern: String,
+    results: Vec<usize>,
+    status: String,
+}
+
+// settings of the trace window
+struct Tracing {
+    // filters written like on the command line, --op DEO
+    filter: String,
+    path: String,
+    status: String,
+}
+
+// fields of the watchpoint form on the debugger
+struct WatchInput {
+    port: bool,
+    // hex address, hex range like 1000-10ff, label or port
+    at: String,
+    access: Access,
+    // optional hex value
+    value: String,
+}
+
+impl WatchInput {
+    fn parse(&self, symbols: &SymbolTable) -> Option<Watchpoint> {
+        let hex = |text: &str| u16::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok();
+
+        let target = if self.port {
+            Target::Port(u8::try_from(hex(&self.at)?).ok()?)
+        } else {
+            match self.at.split_once('-') {
+                Some((start, end)) => Target::Ram(hex(start)?..=hex(end)?),
+                None => {
+                    let addr = symbols.find(self.at.trim()).or_else(|| hex(&self.at))?;
+                    Target::Ram(addr..=addr)
+                }
+            }
+        };
+
+        let value = match self.value.trim() {
+            "" => None,
+            text => Some(hex(text)?),
+        };
+
+        return Some(Watchpoint {
+            target,
+            access: self.access,
+            value,
+        });
+    }
+}
+
+// rom opened from disk through the rom window
+struct Source {
+    path: String,
+    // reload whenever the file changes on disk
+    watch: bool,
+    modified: Option<time::SystemTime>,
+    // when the file was last looked at
+    checked: Option<time::Instant>,
+    // folder shown by the file picker while it is open
+    browsing: Option<PathBuf>,
+    // messages shown on the console window, assembly errors included
+    console: Vec<String>,
+}
+
+impl Source {
+    fn log(&mut self, line: String) {
+        self.console.push(line);
+
+        if self.console.len() > CONSOLE_LINES {
+            let extra = self.console.len() - CONSOLE_LINES;
+            self.console.drain(..extra);
+        }
+    }
+}
 
 /// This is mostly identical to the gain example, minus some fluff, and with a GUI.
 pub struct Gain {
@@ -48,6 +198,17 @@ pub struct GainParams {
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
 
+    /// Arguments handed to the ROM through the console device after the reset vector, separated
+    /// by whitespace.
+    #[persist = "rom-args"]
+    pub rom_args: Arc<RwLock<String>>,
+
+    /// Name the running ROM gave itself through its metadata block. It titles the screen window
+    /// and is kept with the state, the host's preset name is left alone since nih-plug has no way
+    /// to set it.
+    #[persist = "rom-name"]
+    pub rom_name: Arc<RwLock<String>>,
+
     #[id = "gain"]
     pub gain: FloatParam,
 
@@ -73,6 +234,9 @@ impl Default for GainParams {
             // editor_state: EguiState::from_size(WIDTH, HEIGHT),
             editor_state: EguiState::from_size(1000, 600),
 
+            rom_args: Arc::new(RwLock::new(String::new())),
+            rom_name: Arc::new(RwLock::new(String::new())),
+
             // See the main gain example for more details
             gain: FloatParam::new(
                 "Gain",
@@ -113,7 +277,7 @@ impl Plugin for Gain {
     }
 
     fn editor(&self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        let uxn = Mutex::new(UXN::new(WIDTH, HEIGHT));
+        let uxn = Arc::new(Mutex::new(UXN::new(WIDTH, HEIGHT)));
 
         {
             // general tests //
@@ -145,6 +309,9 @@ impl Plugin for Gain {
 
             let mut setup = uxn.lock().unwrap();
 
+            // a stack dump from the rom stops it in the debugger
+            setup.debugger.stop_on_inspect = true;
+
             setup.load(rom);
 
             // make it so that if any error happens
@@ -157,31 +324,110 @@ impl Plugin for Gain {
             // advance into the gui and show that it is booting up
             setup.eval(0x100);
 
-            // we also asume the user did define the system colors
-            // so we set the background color here
-            // TODO make this a runtime thing,
-            // so that we can change colors on the fly
-            setup.bg_color();
+            // once booted, the rom can receive its arguments
+            let args = split_args(&self.params.rom_args.read().unwrap());
+            if !args.is_empty() {
+                crate::devices::console_args(&mut setup, &args);
+            }
         }
 
         let params = self.params.clone();
         let peak_meter = self.peak_meter.clone();
 
-        let memory_widget = Mutex::new(
-            MemoryEditor::new()
-                .with_address_range("Program", 0x100..0x13000)
-                .with_address_range("Zero Page", 0..0x100)
-            );
+        // labels of the running rom
+        let symbols = Mutex::new(SymbolTable::new());
+
+        let memory_widget = Mutex::new(memory_editor(&symbols.lock().unwrap()));
+
+        // where the disassembly window starts, in hex
+        let disasm_start = Mutex::new(String::from("0100"));
+
+        // address or label typed on the debugger, to become a breakpoint
+        let breakpoint_input = Mutex::new(String::new());
+
+        let search = Mutex::new(Search {
+            pattern: String::new(),
+            results: Vec::new(),
+            status: String::new(),
+        });
+
+        let tracing = Mutex::new(Tracing {
+            filter: String::new(),
+            path: String::from("auxn.trace"),
+            status: String::new(),
+        });
+
+        let watch_input = Mutex::new(WatchInput {
+            port: false,
+            at: String::new(),
+            access: Access::Write,
+            value: String::new(),
+        });
+
+        // when set, the uxn screen takes the size of the editor instead of the other way around
+        let follow_window = Mutex::new(false);
+
+        // room the screen window takes around the uxn screen, as of the last frame
+        let window_chrome = Mutex::new(None::<egui::Vec2>);
+
+        let capture = Arc::new(Mutex::new(Capture {
+            path: String::from("screenshot.png"),
+            scale: 1,
+            layers: false,
+            status: String::new(),
+
+            gif_path: String::from("recording.gif"),
+            recorder: None,
+        }));
+
+        let scheduler = Arc::new(Mutex::new(Scheduler::new(SCREEN_RATE, Policy::CatchUp(4))));
+
+        let source = Mutex::new(Source {
+            path: String::new(),
+            watch: false,
+            modified: None,
+            checked: None,
+            browsing: None,
+            console: Vec::new(),
+        });
+
+        // the screen vector runs on its own thread, started every time the editor opens
+        let screen_thread = {
+            let uxn = Arc::downgrade(&uxn);
+            let capture = capture.clone();
+            let scheduler = scheduler.clone();
+            let editor_state = self.params.editor_state.clone();
+            let running = Mutex::new(None::<ScreenThread>);
+
+            move |ctx: &egui::Context| {
+                let mut running = running.lock().unwrap();
+
+                // the thread of a window that was closed and opened again could still be going
+                *running = None;
+
+                let ctx = ctx.clone();
+                let uxn = uxn.clone();
+                let capture = capture.clone();
+                let scheduler = scheduler.clone();
+                let editor_state = editor_state.clone();
+                let stop = Arc::new(AtomicBool::new(false));
+
+                let handle = {
+                    let stop = stop.clone();
+                    thread::spawn(move || run_screen(ctx, uxn, capture, scheduler, editor_state, stop))
+                };
 
-        {
-            let mut mw_setup = memory_widget.lock().unwrap();
-            mw_setup.options.show_ascii = false;
-        }
+                *running = Some(ScreenThread {
+                    stop,
+                    handle: Some(handle),
+                });
+            }
+        };
 
         create_egui_editor(
             self.params.editor_state.clone(),
             (),
-            move |_, _| {},
+            move |ctx, _| screen_thread(ctx),
             move |ctx, setter, _state| {
 
                 egui::CentralPanel::default().show(ctx, |ui| {
@@ -190,31 +436,684 @@ impl Plugin for Gain {
                         let mut cycle = uxn.lock().unwrap();
                         let mut hex = memory_widget.lock().unwrap();              
 
-                        hex.window_ui_read_only(
-                            ctx,
-                            &mut true,
-                            &mut cycle.ram,
-                            |mem, addr| {
-                                mem[addr].into()
-                            },
-                        );
+                        // memory can only be patched while the debugger holds the vm
+                        if cycle.debugger.is_paused() {
+                            let edited = Cell::new(false);
+
+                            hex.window_ui(
+                                ctx,
+                                &mut true,
+                                &mut cycle.ram,
+                                |mem, addr| {
+                                    mem[addr].into()
+                                },
+                                |mem, addr, val| {
+                                    mem[addr] = val;
+                                    edited.set(true);
+                                },
+                            );
+
+                            if edited.get() {
+                                rewind::edit(&mut cycle);
+                            }
+                        } else {
+                            hex.window_ui_read_only(
+                                ctx,
+                                &mut true,
+                                &mut cycle.ram,
+                                |mem, addr| {
+                                    mem[addr].into()
+                                },
+                            );
+                        }
+
+                        let paused = cycle.debugger.is_paused();
+                        let stacks = [("wst", cycle.wst), ("rst", cycle.rst)];
+
+                        let mut edited = false;
+
+                        egui::Window::new("stacks")
+                        .show(ctx, |ui| {
+                            for (name, base) in stacks {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.monospace(name);
+
+                                    // the pointer lives in the last byte of the stack
+                                    let ptr = cycle.ram[base + 0xff];
+                                    let highlight = egui::Color32::YELLOW;
+
+                                    if paused {
+                                        ui.colored_label(highlight, "ptr");
+                                        edited |= ui.add(egui::DragValue::new(&mut cycle.ram[base + 0xff]).custom_formatter(|n, _| format!("{:02x}", n as u8))).changed();
+
+                                        for i in 0..ptr as usize {
+                                            edited |= ui.add(egui::DragValue::new(&mut cycle.ram[base + i]).custom_formatter(|n, _| format!("{:02x}", n as u8))).changed();
+                                        }
+                                    } else {
+                                        ui.colored_label(highlight, format!("ptr {:02x}", ptr));
+
+                                        for i in 0..ptr as usize {
+                                            let text = egui::RichText::new(format!("{:02x}", cycle.ram[base + i])).monospace();
+
+                                            // the top of the stack is where the pointer sits
+                                            if i + 1 == ptr as usize {
+                                                ui.label(text.color(highlight));
+                                            } else {
+                                                ui.label(text);
+                                            }
+                                        }
+                                    }
+                                });
+                            }
+                        });
+
+                        if edited {
+                            rewind::edit(&mut cycle);
+                        }
+
+                        let symbols = symbols.lock().unwrap();
+                        let mut search = search.lock().unwrap();
+
+                        egui::Window::new("search")
+                        .show(ctx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut search.pattern);
+
+                                if ui.button("find").clicked() {
+                                    match debugger::parse_pattern(&search.pattern) {
+                                        Some(pattern) => {
+                                            search.results = debugger::search(&cycle.ram[..0x10000], &pattern, SEARCH_RESULTS);
+                                            search.status = format!("{} found", search.results.len());
+                                        }
+                                        None => {
+                                            search.results.clear();
+                                            search.status = String::from("not a hex pattern");
+                                        }
+                                    }
+                                }
+                            });
+
+                            if !search.status.is_empty() {
+                                ui.label(&search.status);
+                            }
+
+                            for addr in &search.results {
+                                // show the match on the disassembly
+                                if ui.link(symbols.address(*addr as u16)).clicked() {
+                                    *disasm_start.lock().unwrap() = format!("{:04x}", addr);
+                                }
+                            }
+                        });
+                    }
+
+                    {
+                        let cycle = uxn.lock().unwrap();
+                        let symbols = symbols.lock().unwrap();
+                        let mut start = disasm_start.lock().unwrap();
+
+                        egui::Window::new("disassembly")
+                        .show(ctx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("from");
+                                ui.text_edit_singleline(&mut *start);
+                            });
+
+                            let from = usize::from_str_radix(start.trim_start_matches("0x"), 16).unwrap_or(0x100).min(0xffff);
+                            let instructions = disassembler::disassemble(&cycle.ram, from..from + DISASM_BYTES, &symbols);
+
+                            egui::ScrollArea::vertical()
+                            .show(ui, |ui| {
+                                ui.monospace(disassembler::listing(&instructions, &symbols));
+                            });
+                        });
+                    }
+
+
+                    let mut source = source.lock().unwrap();
+                    let mut open = false;
+
+                    egui::Window::new("rom")
+                    .show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut source.path);
+                            open = ui.button("open").clicked();
+
+                            if ui.button("browse").clicked() {
+                                source.browsing = match source.browsing {
+                                    Some(_) => None,
+                                    None => Some(browse_start(&source.path)),
+                                };
+                            }
+                        });
+
+                        ui.checkbox(&mut source.watch, "watch");
+
+                        if let Some(dir) = source.browsing.clone() {
+                            ui.separator();
+                            ui.monospace(dir.display().to_string());
+
+                            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                if let Some(parent) = dir.parent() {
+                                    if ui.link("..").clicked() {
+                                        source.browsing = Some(parent.to_path_buf());
+                                    }
+                                }
+
+                                for (path, is_dir) in browse(&dir) {
+                                    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+                                    if is_dir {
+                                        if ui.link(format!("{}/", name)).clicked() {
+                                            source.browsing = Some(path);
+                                        }
+                                    } else if ui.link(name).clicked() {
+                                        source.path = path.display().to_string();
+                                        source.browsing = None;
+                                        open = true;
+                                    }
+                                }
+                            });
+                        }
+                    });
+
+                    if source.watch {
+                        let interval = time::Duration::from_millis(WATCH_INTERVAL_MS);
+                        let now = time::Instant::now();
+
+                        // repaints come much more often than the file needs looking at
+                        if source.checked.map_or(true, |at| now.duration_since(at) >= interval) {
+                            source.checked = Some(now);
+
+                            let modified = modified(&source.path);
+                            if modified.is_some() && modified != source.modified {
+                                open = true;
+                            }
+                        }
+
+                        // keep polling even when the rom draws nothing
+                        ctx.request_repaint_after(interval);
                     }
 
+                    // read and assembled before the machine gets locked, so the screen keeps going meanwhile
+                    let loaded = if open {
+                        let path = PathBuf::from(&source.path);
+                        source.modified = modified(&source.path);
+
+                        Some((read_rom(&path), path))
+                    } else {
+                        None
+                    };
 
                     let mut cycle = uxn.lock().unwrap();
-                    let screen_vector_addr = cycle.screen.vector();
+                    let mut capture = capture.lock().unwrap();
+
+                    if let Some((result, path)) = loaded {
+                        match result {
+                            Ok((rom, table)) => {
+                                let size = rom.len();
+                                boot(&mut cycle, rom, &split_args(&params.rom_args.read().unwrap()));
+
+                                source.log(format!("loaded {} ({} bytes, {} labels)", path.display(), size, table.len()));
+
+                                *memory_widget.lock().unwrap() = memory_editor(&table);
+                                *symbols.lock().unwrap() = table;
+                            }
+                            Err(e) => source.log(e),
+                        }
+                    }
+
+                    {
+                        let symbols = symbols.lock().unwrap();
+                        let mut input = breakpoint_input.lock().unwrap();
+                        let mut watch = watch_input.lock().unwrap();
+
+                        egui::Window::new("debugger")
+                        .show(ctx, |ui| {
+                            ui.horizontal(|ui| {
+                                if cycle.debugger.is_paused() {
+                                    if ui.button("continue").clicked() {
+                                        debugger::debug_continue(&mut cycle);
+                                    }
+                                } else if ui.button("pause").clicked() {
+                                    debugger::debug_pause(&mut cycle, "paused");
+                                }
+
+                                if ui.button("step").clicked() {
+                                    debugger::debug_step(&mut cycle);
+                                }
+
+                                if ui.button("step over").clicked() {
+                                    debugger::debug_step_over(&mut cycle);
+                                }
+
+                                if ui.button("step out").clicked() {
+                                    debugger::debug_step_out(&mut cycle);
+                                }
+                            });
+
+                            if !cycle.debugger.reason.is_empty() {
+                                ui.label(&cycle.debugger.reason);
+                            }
+
+                            // the watchpoint stopped on the instruction after the one that set it off
+                            if let Some(trigger) = cycle.debugger.trigger {
+                                if cycle.debugger.is_paused() {
+                                    ui.monospace(format!("by {}", symbols.address(trigger)));
+                                }
+                            }
+
+                            match cycle.debugger.pc {
+                                Some(pc) => {
+                                    ui.monospace(format!("pc {}", symbols.address(pc as u16)));
+
+                                    let instructions = disassembler::disassemble(&cycle.ram, pc..pc + DEBUGGER_LINES * 3, &symbols);
+                                    for (i, instr) in instructions.iter().take(DEBUGGER_LINES).enumerate() {
+                                        let marker = if i == 0 { ">" } else { " " };
+                                        ui.monospace(format!("{} {:04x}  {}", marker, instr.addr, instr.text));
+                                    }
+
+                                    ui.monospace(crate::devices::inspect(&cycle));
+
+                                    // where each routine on the return stack goes back to
+                                    if !symbols.is_empty() {
+                                        for addr in return_addresses(&cycle) {
+                                            ui.monospace(format!("return {}", symbols.address(addr)));
+                                        }
+                                    }
+                                }
+                                None => match cycle.system.fault {
+                                    Some(fault) => {
+                                        ui.monospace(fault.report(&symbols));
+                                        ui.monospace(crate::devices::inspect(&cycle));
+                                    }
+                                    None => {
+                                        ui.label("running");
+                                    }
+                                },
+                            }
+
+                            ui.separator();
+
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut *input);
+
+                                // either a label or a hex address
+                                if ui.button("add breakpoint").clicked() {
+                                    let addr = symbols.find(input.trim())
+                                        .or_else(|| u16::from_str_radix(input.trim().trim_start_matches("0x"), 16).ok());
+
+                                    if let Some(addr) = addr {
+                                        cycle.debugger.breakpoints.insert(addr);
+                                        input.clear();
+                                    }
+                                }
+                            });
+
+                            let mut remove = None;
+                            for addr in &cycle.debugger.breakpoints {
+                                ui.horizontal(|ui| {
+                                    ui.monospace(symbols.address(*addr));
+
+                                    if ui.small_button("x").clicked() {
+                                        remove = Some(*addr);
+                                    }
+                                });
+                            }
+
+                            if let Some(addr) = remove {
+                                cycle.debugger.breakpoints.remove(&addr);
+                            }
+
+                            ui.separator();
+
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_source("watch target")
+                                .selected_text(if watch.port { "port" } else { "ram" })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut watch.port, false, "ram");
+                                    ui.selectable_value(&mut watch.port, true, "port");
+                                });
+
+                                ui.add(egui::TextEdit::singleline(&mut watch.at).desired_width(80.0));
+
+                                egui::ComboBox::from_id_source("watch access")
+                                .selected_text(match watch.access {
+                                    Access::Read => "read",
+                                    Access::Write => "write",
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut watch.access, Access::Read, "read");
+                                    ui.selectable_value(&mut watch.access, Access::Write, "write");
+                                });
+
+                                ui.label("=");
+                                ui.add(egui::TextEdit::singleline(&mut watch.value).desired_width(40.0));
+
+                                if ui.button("add watchpoint").clicked() {
+                                    if let Some(point) = watch.parse(&symbols) {
+                                        cycle.debugger.watchpoints.push(point);
+                                        watch.at.clear();
+                                        watch.value.clear();
+                                    }
+                                }
+                            });
+
+                            let mut remove = None;
+                            for (i, point) in cycle.debugger.watchpoints.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.monospace(point.to_string());
+
+                                    if ui.small_button("x").clicked() {
+                                        remove = Some(i);
+                                    }
+                                });
+                            }
+
+                            if let Some(i) = remove {
+                                cycle.debugger.watchpoints.remove(i);
+                            }
+                        });
+                    }
+
+                    {
+                        let symbols = symbols.lock().unwrap();
+                        let mut tracing = tracing.lock().unwrap();
+
+                        egui::Window::new("trace")
+                        .show(ctx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("filter");
+                                ui.add_enabled(!cycle.trace.is_recording(), egui::TextEdit::singleline(&mut tracing.filter));
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(!cycle.trace.is_recording(), egui::TextEdit::singleline(&mut tracing.path));
+
+                                if cycle.trace.is_recording() {
+                                    if ui.button(format!("stop ({} instructions)", cycle.trace.count)).clicked() {
+                                        tracing.status = match cycle.trace.stop() {
+                                            Ok(()) => format!("stopped after {} instructions", cycle.trace.count),
+                                            Err(e) => format!("could not save {}: {}", tracing.path, e),
+                                        };
+                                    }
+                                } else {
+                                    let filter = trace::Filter::parse(&split_args(&tracing.filter));
+
+                                    let memory = ui.button("record").clicked();
+                                    let file = ui.button("record to file").clicked();
+
+                                    match filter {
+                                        Ok(filter) if memory || file => {
+                                            cycle.trace.filter = filter;
+
+                                            if memory {
+                                                cycle.trace.record_to_ring(TRACE_CAPACITY);
+                                                tracing.status = String::from("recording");
+                                            } else {
+                                                let path = std::path::PathBuf::from(&tracing.path);
+                                                tracing.status = match cycle.trace.record_to_file(&path) {
+                                                    Ok(()) => format!("recording to {}", path.display()),
+                                                    Err(e) => format!("could not record {}: {}", path.display(), e),
+                                                };
+                                            }
+                                        }
+                                        Err(e) if memory || file => tracing.status = e,
+                                        _ => {}
+                                    }
+                                }
+                            });
+
+                            if let Some(e) = &cycle.trace.error {
+                                ui.label(format!("recording failed: {}", e));
+                            } else if !tracing.status.is_empty() {
+                                ui.label(&tracing.status);
+                            }
+
+                            for record in cycle.trace.last(TRACE_LINES) {
+                                ui.monospace(record.pretty(&symbols));
+                            }
+                        });
+                    }
+
+                    egui::Window::new("timeline")
+                    .show(ctx, |ui| {
+                        let (oldest, latest) = (cycle.timeline.oldest(), cycle.timeline.latest);
+                        let mut frame = cycle.timeline.frame;
+                        let mut result = Ok(());
 
-                    // return a result
-                    // if we have an error, show a notification
-                    cycle.eval(screen_vector_addr);
+                        // dragging back holds the machine on that frame
+                        if ui.add(egui::Slider::new(&mut frame, oldest..=latest).text("frame")).changed() {
+                            result = rewind::seek(&mut cycle, frame);
+
+                            if result.is_ok() {
+                                cycle.timeline.scrubbing = true;
+                            }
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button("step back").clicked() {
+                                result = rewind::step_back(&mut cycle);
+                            }
+
+                            if ui.button("step forward").clicked() {
+                                result = rewind::step_forward(&mut cycle);
+                            }
+
+                            // whatever came after this frame is replaced from here on
+                            if ui.add_enabled(cycle.timeline.scrubbing, egui::Button::new("resume")).clicked() {
+                                cycle.timeline.scrubbing = false;
+                            }
+                        });
+
+                        if let Err(e) = result {
+                            ui.label(e);
+                        } else if cycle.timeline.scrubbing {
+                            ui.label(format!("holding frame {} of {}", cycle.timeline.frame, latest));
+                        }
+                    });
+
+                    egui::Window::new("console")
+                    .show(ctx, |ui| {
+                        egui::ScrollArea::vertical()
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for line in &source.console {
+                                ui.monospace(line);
+                            }
+                        });
+                    });
 
                     if cycle.screen.redraw {
                         cycle.screen.generate(ctx);
                         cycle.screen.redraw = false;
+
+                        if let Some(recorder) = &mut capture.recorder {
+                            if let Err(e) = recorder.capture(&cycle.screen) {
+                                capture.status = format!("recording failed: {}", e);
+                                capture.recorder = None;
+                            }
+                        }
                     }
 
-                    egui::Window::new("screen")
+                    egui::Window::new("arguments")
+                    .show(ctx, |ui| {
+                        let mut rom_args = params.rom_args.write().unwrap();
+
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut *rom_args);
+
+                            // resend them without having to reopen the editor,
+                            // a vector held by the debugger has to finish first
+                            if ui.add_enabled(!cycle.debugger.is_paused(), egui::Button::new("send")).clicked() {
+                                let args = split_args(&rom_args);
+                                if !args.is_empty() {
+                                    rewind::input(&mut cycle, Input::ConsoleArgs(args));
+                                }
+                            }
+                        });
+                    });
+
+                    egui::Window::new("capture")
                     .show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut capture.path);
+                            ui.add(egui::DragValue::new(&mut capture.scale).clamp_range(1..=8).suffix("x"));
+                        });
+
+                        ui.checkbox(&mut capture.layers, "separate layers");
+
+                        if ui.button("save screenshot").clicked() {
+                            let path = std::path::PathBuf::from(&capture.path);
+
+                            let mut result = cycle.screen.screenshot(&path, capture.scale);
+                            if result.is_ok() && capture.layers {
+                                result = cycle.screen.save_layers(&path);
+                            }
+
+                            capture.status = match result {
+                                Ok(()) => format!("saved {}", path.display()),
+                                Err(e) => format!("could not save {}: {}", path.display(), e),
+                            };
+                        }
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(capture.recorder.is_none(), egui::TextEdit::singleline(&mut capture.gif_path));
+
+                            match capture.recorder.take() {
+                                Some(recorder) => {
+                                    if ui.button(format!("stop ({} frames)", recorder.frames)).clicked() {
+                                        let frames = recorder.frames;
+
+                                        capture.status = match recorder.stop() {
+                                            Ok(()) => format!("saved {} ({} frames)", capture.gif_path, frames + 1),
+                                            Err(e) => format!("could not save {}: {}", capture.gif_path, e),
+                                        };
+                                    } else {
+                                        capture.recorder = Some(recorder);
+                                    }
+                                }
+
+                                None => {
+                                    if ui.button("record").clicked() {
+                                        let path = std::path::PathBuf::from(&capture.gif_path);
+
+                                        match Recorder::start(&path, &cycle.screen) {
+                                            Ok(recorder) => {
+                                                capture.recorder = Some(recorder);
+                                                capture.status = format!("recording {}", path.display());
+                                            }
+                                            Err(e) => {
+                                                capture.status = format!("could not record {}: {}", path.display(), e);
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        });
+
+                        if !capture.status.is_empty() {
+                            ui.label(&capture.status);
+                        }
+                    });
+
+                    // name the screen after the rom when it tells us how it is called
+                    let title = match &cycle.system.meta {
+                        Some(meta) => {
+                            let mut rom_name = params.rom_name.write().unwrap();
+                            if *rom_name != meta.name {
+                                *rom_name = meta.name.clone();
+                            }
+
+                            meta.name.clone()
+                        }
+                        None => String::from("auxn"),
+                    };
+
+                    let mut follow = follow_window.lock().unwrap();
+                    let mut chrome = window_chrome.lock().unwrap();
+
+                    let mut window = egui::Window::new(title)
+                    .id(egui::Id::new("screen"))
+                    .resizable(false);
+
+                    // while following, the window is pinned to the corner and the editor sets its size
+                    if *follow {
+                        window = window.fixed_pos(egui::Pos2::ZERO);
+                    }
+
+                    let shown = window.show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut *follow, "follow window")
+                            .on_hover_text("the uxn screen takes the size of the editor. a rom changing its screen size only resizes this window, the editor can't be resized from here");
+
+                            let mut zoom = cycle.screen.zoom;
+                            egui::ComboBox::from_label("zoom")
+                            .selected_text(match zoom {
+                                Zoom::Scale(k) => format!("{}x", k),
+                                Zoom::Fit => String::from("fit"),
+                            })
+                            .show_ui(ui, |ui| {
+                                for k in 1..=4 {
+                                    ui.selectable_value(&mut zoom, Zoom::Scale(k), format!("{}x", k));
+                                }
+                                ui.selectable_value(&mut zoom, Zoom::Fit, "fit");
+                            });
+                            cycle.screen.zoom = zoom;
+
+                            let mut scheduler = scheduler.lock().unwrap();
+                            let mut policy = scheduler.policy;
+                            egui::ComboBox::from_label("late frames")
+                            .selected_text(match policy {
+                                Policy::CatchUp(_) => "catch up",
+                                Policy::Drop => "drop",
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut policy, Policy::CatchUp(4), "catch up");
+                                ui.selectable_value(&mut policy, Policy::Drop, "drop");
+                            });
+                            scheduler.policy = policy;
+
+                            let mut crt = cycle.screen.crt;
+                            if ui.checkbox(&mut crt, "crt").changed() {
+                                cycle.screen.crt = crt;
+                                cycle.screen.touch_all();
+                                cycle.screen.generate(ctx);
+                                cycle.screen.redraw = false;
+                            }
+                        });
+
+                        // whatever of the editor is left once the window around the screen is drawn,
+                        // unknown until the window was shown once
+                        let (width, height) = params.editor_state.size();
+                        let room = chrome.map(|chrome| egui::Vec2::new(width as f32, height as f32) - chrome);
+
+                        if let (true, Some(room)) = (*follow, room) {
+                            // every uxn pixel keeps its zoom while following
+                            let k = match cycle.screen.zoom {
+                                Zoom::Scale(k) => k as f32,
+                                Zoom::Fit => 1.0,
+                            };
+
+                            let size = room / k;
+                            let (w, h) = (size.x as u32, size.y as u32);
+
+                            if w >= 8 && h >= 8 && (w != cycle.screen.width || h != cycle.screen.height) {
+                                rewind::input(&mut cycle, Input::Resize(w, h));
+                                cycle.screen.generate(ctx);
+                                cycle.screen.redraw = false;
+                            }
+                        }
+
+                        let size = match (*follow, room) {
+                            (true, Some(room)) => cycle.screen.display_size(room),
+                            _ => cycle.screen.display_size(ui.available_size()),
+                        };
+
+                        // the crt look gets rebuilt when the zoom it was made for changes
+                        cycle.screen.generate(ctx);
+
                         let texture = cycle.screen.display.as_ref().expect("No Texture Loaded");
 
                         // let im = egui::Image::new(texture, texture.size_vec2())
@@ -226,7 +1125,7 @@ impl Plugin for Gain {
                         //     }
                         // ).ui(ui);
 
-                        let im = ui.image(texture, texture.size_vec2());
+                        let im = ui.image(texture, size);
 
                         let pos = ctx.input().pointer.hover_pos().unwrap_or_default();
                         if im.rect.contains(pos) {
@@ -242,27 +1141,43 @@ impl Plugin for Gain {
                                 let mut x = if pos.x >= min.x { if pos.x <= max.x { pos.x } else { max.x } } else { min.x };
                                 let mut y = if pos.y >= min.y { if pos.y <= max.y { pos.y } else { max.y } } else { min.y };
 
+                                // map the pointer back through the zoom
+                                let scale = im.rect.width() / cycle.screen.width as f32;
+
                                 // poke mouse coords
-                                crate::devices::mouse_pos(&mut cycle, x - min.x, y - min.y);
+                                rewind::input(&mut cycle, Input::MousePos((x - min.x) / scale, (y - min.y) / scale));
                             }
 
                             if ctx.input().pointer.any_pressed() {
                                 // poke pressed mouse btn
-                                crate::devices::mouse_down(&mut cycle);
+                                rewind::input(&mut cycle, Input::MouseDown);
                             }
 
                             if ctx.input().pointer.any_released() {
                                 // poke released mouse btn
-                                crate::devices::mouse_up(&mut cycle);
+                                rewind::input(&mut cycle, Input::MouseUp);
                             }          
                         } else {
                             ui.output().cursor_icon = CursorIcon::Crosshair;
                         }
 
+                        if let Some(code) = cycle.system.exit {
+                            ui.label(format!("halted (code {})", code));
+                        }
+
+                        if let Some(fault) = cycle.system.fault {
+                            ui.label(format!("stopped: {}", fault.report(&symbols.lock().unwrap())));
+                        }
+
                         // if ctx.is_pointer_over_area() {}
 
+                        return im.rect.size();
                     });
 
+                    if let Some(egui::InnerResponse { inner: Some(image), response }) = shown {
+                        *chrome = Some(response.rect.size() - image);
+                    }
+
                     // egui::Window::new("debug")
                     // .show(ctx, |ui| {
                     //     ctx.texture_ui(ui);
@@ -332,6 +1247,178 @@ impl Plugin for Gain {
     }
 }
 
+// fire the screen vector at a steady rate until the window closes or another one opens,
+// the editor only gets repainted when the rom actually drew something
+fn run_screen(
+    ctx: egui::Context,
+    uxn: Weak<Mutex<UXN>>,
+    capture: Arc<Mutex<Capture>>,
+    scheduler: Arc<Mutex<Scheduler>>,
+    editor_state: Arc<EguiState>,
+    stop: Arc<AtomicBool>,
+) {
+    scheduler.lock().unwrap().restart(time::Instant::now());
+
+    // the window is only marked as open once it finished opening, which can be after this starts
+    let mut opened = false;
+
+    while !stop.load(Ordering::Relaxed) {
+        if editor_state.is_open() {
+            opened = true;
+        } else if opened {
+            stop.store(true, Ordering::Relaxed);
+            break;
+        }
+
+        let uxn = match uxn.upgrade() {
+            Some(uxn) => uxn,
+            None => break,
+        };
+
+        let frames = scheduler.lock().unwrap().due(time::Instant::now());
+
+        {
+            let mut cycle = uxn.lock().unwrap();
+            let mut capture = capture.lock().unwrap();
+            let paused = cycle.debugger.is_paused();
+
+            for _ in 0..frames {
+                // nothing runs while the debugger holds a vector
+                if cycle.debugger.is_paused() {
+                    break;
+                }
+
+                // return a result
+                // if we have an error, show a notification
+                rewind::frame(&mut cycle);
+
+                // the gif follows the pace of the screen vector
+                if let Some(recorder) = &mut capture.recorder {
+                    recorder.tick();
+                }
+            }
+
+            // the debugger panel has to show where it stopped
+            if cycle.screen.redraw || cycle.debugger.is_paused() != paused {
+                ctx.request_repaint();
+            }
+        }
+
+        drop(uxn);
+
+        let wait = scheduler.lock().unwrap().until_next(time::Instant::now());
+        thread::sleep(wait);
+    }
+}
+
+pub fn split_args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+// read a rom from disk, assembling it on the way when it is uxntal source,
+// along with its labels
+pub fn read_rom(path: &Path) -> Result<(Vec<u8>, SymbolTable), String> {
+    if path.extension().map_or(false, |ext| ext == "tal") {
+        return assembler::assemble_file(path)
+            .map(|assembly| (assembly.rom, SymbolTable::from_entries(&assembly.symbols)))
+            .map_err(|e| e.to_string());
+    }
+
+    let rom = fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+    return Ok((rom, SymbolTable::for_rom(path)));
+}
+
+// the memory view, with a range for every top level label
+fn memory_editor(symbols: &SymbolTable) -> MemoryEditor {
+    let mut editor = MemoryEditor::new()
+        .with_address_range("Program", 0x100..0x13000)
+        .with_address_range("Zero Page", 0..0x100)
+        .with_address_range("Working Stack", 0x10000..0x10100)
+        .with_address_range("Return Stack", 0x11000..0x11100)
+        .with_address_range("Devices", 0x12000..0x12100);
+
+    let labels: Vec<(u16, &str)> = symbols.iter().filter(|(_, name)| !name.contains('/')).collect();
+
+    // each label spans until the next one
+    for (i, (addr, name)) in labels.iter().enumerate() {
+        let end = labels.get(i + 1).map_or(0x10000, |(next, _)| *next as usize);
+
+        if end > *addr as usize {
+            editor = editor.with_address_range(format!("@{}", name), *addr as usize..end);
+        }
+    }
+
+    editor.options.show_ascii = false;
+
+    return editor;
+}
+
+// shorts on the return stack, top first
+fn return_addresses(uxn: &UXN) -> Vec<u16> {
+    let ptr = uxn.ram[uxn.rst + 0xff] as usize;
+
+    return (0..ptr / 2)
+        .rev()
+        .map(|i| u16::from_be_bytes([uxn.ram[uxn.rst + i * 2], uxn.ram[uxn.rst + i * 2 + 1]]))
+        .collect();
+}
+
+// start over with another rom, the screen keeps its size and look
+fn boot(uxn: &mut UXN, rom: Vec<u8>, args: &[String]) {
+    let mut fresh = UXN::new(uxn.screen.width, uxn.screen.height);
+    fresh.screen.zoom = uxn.screen.zoom;
+    fresh.screen.crt = uxn.screen.crt;
+
+    // breakpoints can catch the new reset vector
+    fresh.debugger.breakpoints = mem::take(&mut uxn.debugger.breakpoints);
+    fresh.debugger.watchpoints = mem::take(&mut uxn.debugger.watchpoints);
+    fresh.debugger.stop_on_inspect = uxn.debugger.stop_on_inspect;
+
+    // and a trace that is recording goes on into the new rom
+    fresh.trace = mem::replace(&mut uxn.trace, trace::Trace::new());
+
+    fresh.load(rom);
+    fresh.eval(0x100);
+
+    if !args.is_empty() {
+        crate::devices::console_args(&mut fresh, args);
+    }
+
+    *uxn = fresh;
+}
+
+fn modified(path: &str) -> Option<time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// the file picker opens on the folder of the current path, or the working directory
+fn browse_start(path: &str) -> PathBuf {
+    let dir = Path::new(path).parent().filter(|dir| dir.is_dir());
+
+    return match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir().unwrap_or_default(),
+    };
+}
+
+// folders first, then the sources and roms, each sorted by name
+fn browse(dir: &Path) -> Vec<(PathBuf, bool)> {
+    let mut entries: Vec<(PathBuf, bool)> = match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|entry| (entry.path(), entry.path().is_dir())).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    entries.retain(|(path, is_dir)| {
+        let ext = path.extension().and_then(|ext| ext.to_str());
+        *is_dir || matches!(ext, Some("tal") | Some("rom"))
+    });
+
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    return entries;
+}
+
 impl ClapPlugin for Gain {
     const CLAP_ID: &'static str = "com.moist-plugins-gmbh-egui.gain-gui";
     const CLAP_DESCRIPTION: Option<&'static str> = Some("A smoothed gain parameter example plugin");
@@ -1,12 +1,236 @@
-mod devices;
-mod operations;
-mod system;
-mod uxn;
-
 use nih_plug::prelude::*;
 
-use auxn::Gain;
+use auxn::disassembler;
+use auxn::symbols::SymbolTable;
+use auxn::trace::{self, Filter};
+use auxn::uxn::UXN;
+use auxn::{Gain, HEIGHT, WIDTH};
+
+use std::path::Path;
+use std::{env, fs, process};
 
 fn main() {
-    nih_export_standalone::<Gain>();
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("run") => run(&args[2..]),
+        Some("asm") => asm(&args[2..]),
+        Some("disasm") => disasm(&args[2..]),
+        Some("trace") => trace(&args[2..]),
+        Some("trace-show") => trace_show(&args[2..]),
+        Some("trace-diff") => trace_diff(&args[2..]),
+        _ => nih_export_standalone::<Gain>(),
+    }
+}
+
+// auxn run <rom> [args...]
+fn run(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: auxn run <rom> [args...]");
+            process::exit(1);
+        }
+    };
+
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let mut uxn = UXN::new(WIDTH, HEIGHT);
+    uxn.load(rom);
+    uxn.eval(0x100);
+
+    if args.len() > 1 {
+        auxn::devices::console_args(&mut uxn, &args[1..]);
+    }
+
+    if let Some(fault) = uxn.system.fault {
+        eprintln!("{}", fault.report(&SymbolTable::for_rom(Path::new(path))));
+        eprint!("{}", auxn::devices::inspect(&uxn));
+        process::exit(1);
+    }
+
+    // let scripts know whether the rom passed
+    if let Some(code) = uxn.system.exit {
+        process::exit(code.into());
+    }
+}
+
+// auxn asm <in.tal> <out.rom>, also writes <out.rom>.sym
+fn asm(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => {
+            eprintln!("usage: auxn asm <in.tal> <out.rom>");
+            process::exit(1);
+        }
+    };
+
+    let assembly = match auxn::assembler::assemble_file(Path::new(input)) {
+        Ok(assembly) => assembly,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let sym = format!("{}.sym", output);
+    if let Err(e) = fs::write(output, &assembly.rom).and_then(|_| fs::write(&sym, assembly.sym())) {
+        eprintln!("could not write {}: {}", output, e);
+        process::exit(1);
+    }
+
+    println!("assembled {} in {} bytes, {} labels", output, assembly.rom.len(), assembly.symbols.len());
+}
+
+// auxn disasm <rom> [sym]
+fn disasm(args: &[String]) {
+    let (path, sym) = match args {
+        [path] => (path, None),
+        [path, sym] => (path, Some(sym)),
+        _ => {
+            eprintln!("usage: auxn disasm <rom> [sym]");
+            process::exit(1);
+        }
+    };
+
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let symbols = match sym {
+        Some(sym) => match SymbolTable::load(Path::new(sym)) {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                eprintln!("could not read {}: {}", sym, e);
+                process::exit(1);
+            }
+        },
+        None => SymbolTable::for_rom(Path::new(path)),
+    };
+
+    // lay the rom out like it would be in memory so addresses line up
+    let mut mem = vec![0; 0x100];
+    mem.extend_from_slice(&rom);
+
+    let instructions = disassembler::disassemble(&mem, 0x100..mem.len(), &symbols);
+    print!("{}", disassembler::listing(&instructions, &symbols));
+}
+
+// auxn trace <rom> <out.trace> [frames] [--range a-b] [--vector v] [--op NAME] [--stacks]
+fn trace(args: &[String]) {
+    if args.len() < 2 {
+        eprintln!("usage: auxn trace <rom> <out.trace> [frames] [--range a-b] [--vector v] [--op NAME] [--stacks]");
+        process::exit(1);
+    }
+
+    let (path, output) = (&args[0], &args[1]);
+
+    // the frame count is optional, the filters come after it
+    let (frames, flags) = match args.get(2).map(|s| s.parse::<usize>()) {
+        Some(Ok(frames)) => (frames, &args[3..]),
+        _ => (0, &args[2..]),
+    };
+
+    let filter = match Filter::parse(flags) {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let mut uxn = UXN::new(WIDTH, HEIGHT);
+    uxn.trace.filter = filter;
+
+    if let Err(e) = uxn.trace.record_to_file(Path::new(output)) {
+        eprintln!("could not write {}: {}", output, e);
+        process::exit(1);
+    }
+
+    let mut uxn = auxn::headless::run_on(uxn, rom, frames);
+
+    if let Some(e) = uxn.trace.error.take().or_else(|| uxn.trace.stop().err().map(|e| e.to_string())) {
+        eprintln!("could not write {}: {}", output, e);
+        process::exit(1);
+    }
+
+    println!("traced {} instructions to {}", uxn.trace.count, output);
+}
+
+// auxn trace-show <file.trace> [sym]
+fn trace_show(args: &[String]) {
+    let (path, symbols) = match args {
+        [path] => (path, SymbolTable::new()),
+        [path, sym] => (path, SymbolTable::load(Path::new(sym)).unwrap_or_else(|e| {
+            eprintln!("could not read {}: {}", sym, e);
+            process::exit(1);
+        })),
+        _ => {
+            eprintln!("usage: auxn trace-show <file.trace> [sym]");
+            process::exit(1);
+        }
+    };
+
+    let records = match trace::read_trace(Path::new(path)) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    for record in records {
+        println!("{}", record.pretty(&symbols));
+    }
+}
+
+// auxn trace-diff <a.trace> <b.trace> [sym], exits with 1 when they differ
+fn trace_diff(args: &[String]) {
+    let (a, b, symbols) = match args {
+        [a, b] => (a, b, SymbolTable::new()),
+        [a, b, sym] => (a, b, SymbolTable::load(Path::new(sym)).unwrap_or_else(|e| {
+            eprintln!("could not read {}: {}", sym, e);
+            process::exit(1);
+        })),
+        _ => {
+            eprintln!("usage: auxn trace-diff <a.trace> <b.trace> [sym]");
+            process::exit(1);
+        }
+    };
+
+    let read = |path: &String| match trace::read_trace(Path::new(path)) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let (a, b) = (read(a), read(b));
+
+    match trace::diff_report(&a, &b, trace::DIFF_CONTEXT, &symbols) {
+        Some(report) => {
+            print!("{}", report);
+            process::exit(1);
+        }
+        None => println!("traces match ({} instructions)", a.len()),
+    }
 }
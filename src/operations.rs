@@ -1,3 +1,4 @@
+use crate::fault::{Error, Stack};
 use crate::uxn::UXN;
 
 impl UXN {
@@ -16,7 +17,13 @@ impl UXN {
     }
 
     pub fn DEI(&self, port: usize) -> u8 {
-        return self.ram[self.dev + port];
+        match port & 0xF0 {
+            0x00 => crate::devices::system_read(self, port),
+
+            0x20 => crate::devices::screen_read(self, port),
+
+            _ => self.ram[self.dev + port],
+        }
     }
 
     pub fn DEO(&mut self, port: usize, val: u8) {
@@ -65,6 +72,21 @@ impl UXN {
         }
     }
 
+    // the first error of an instruction is the one reported
+    pub fn raise(&mut self, error: Error) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    pub fn stack(&self, addr: usize) -> Stack {
+        if addr == self.wst {
+            Stack::Working
+        } else {
+            Stack::Return
+        }
+    }
+
     pub fn JUMP(&self, addr: usize, pc: usize) -> usize {
         if self.r2 {
             return addr;
@@ -83,8 +105,9 @@ impl UXN {
 
     pub fn DST_PUSH8(&mut self, s: u8) {
         if self.dst_ptr() == 0xff {
-            panic!("OVERFLOW");
+            return self.raise(Error::Overflow(self.stack(self.dst)));
         }
+
         let index = self.dst_inc();
         self.ram[self.dst + index] = s;
     }
@@ -99,7 +122,7 @@ impl UXN {
 
     pub fn PUSH8(&mut self, s: u8) {
         if self.ptr() == 0xff {
-            panic!("OVERFLOW");
+            return self.raise(Error::Overflow(self.stack(self.src)));
         }
 
         let index = self.inc();
@@ -122,8 +145,12 @@ impl UXN {
     }
 
     pub fn POP8(&mut self) -> u8 {
-        if self.ptr() == 0x00 {
-            panic!("UNDERFLOW");
+        // keep-mode reads below its own copy of the pointer
+        let ptr = if self.rk { self.pk } else { self.ptr() as usize };
+
+        if ptr == 0 {
+            self.raise(Error::Underflow(self.stack(self.src)));
+            return 0;
         }
 
         let index = self.dec();
@@ -0,0 +1,286 @@
+// going back in time
+//
+// every few frames the whole machine gets copied, and every input that reaches the rom is
+// logged with the frame it arrived on. going back to a frame restores the closest copy
+// before it and replays the frames in between with the same inputs
+
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::debugger::Debugger;
+use crate::devices::*;
+use crate::trace::Trace;
+use crate::uxn::UXN;
+
+// frames between two snapshots
+pub const SNAPSHOT_INTERVAL: u64 = 60;
+// snapshots kept, older ones are dropped along with their inputs
+pub const SNAPSHOT_CAPACITY: usize = 60;
+
+#[derive(Clone)]
+pub enum Input {
+    MousePos(f32, f32),
+    MouseDown,
+    MouseUp,
+    ConsoleArgs(Vec<String>),
+    Resize(u32, u32),
+}
+
+// the machine in between two vectors
+pub struct Snapshot {
+    pub frame: u64,
+
+    ram: Vec<u8>,
+    system: SystemDevice,
+    console: ConsoleDevice,
+    screen: ScreenDevice,
+    mouse: MouseDevice,
+}
+
+impl Snapshot {
+    pub fn take(uxn: &UXN, frame: u64) -> Self {
+        let mut screen = uxn.screen.clone();
+        // the texture belongs to the editor, not to the machine
+        screen.display = None;
+
+        Snapshot {
+            frame,
+
+            ram: uxn.ram.to_vec(),
+            system: uxn.system.clone(),
+            console: uxn.console.clone(),
+            screen,
+            mouse: uxn.mouse.clone(),
+        }
+    }
+
+    fn restore(&self, uxn: &mut UXN) {
+        uxn.ram.copy_from_slice(&self.ram);
+        uxn.system = self.system.clone();
+        uxn.console = self.console.clone();
+        uxn.mouse = self.mouse.clone();
+
+        // keep the texture and how the screen is shown, then upload it all again
+        let mut screen = self.screen.clone();
+        screen.display = uxn.screen.display.take();
+        screen.zoom = uxn.screen.zoom;
+        screen.crt = uxn.screen.crt;
+        uxn.screen = screen;
+        uxn.screen.touch_all();
+
+        uxn.halted = false;
+    }
+}
+
+pub struct Timeline {
+    // screen vectors run since boot
+    pub frame: u64,
+    // the furthest frame that was reached
+    pub latest: u64,
+    // holding still on an earlier frame, nothing runs until resumed
+    pub scrubbing: bool,
+    // memory was changed by hand while the debugger held a vector
+    pub edited: bool,
+
+    snapshots: VecDeque<Snapshot>,
+    inputs: Vec<(u64, Input)>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            frame: 0,
+            latest: 0,
+            scrubbing: false,
+            edited: false,
+
+            snapshots: VecDeque::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    // the earliest frame that can still be reached
+    pub fn oldest(&self) -> u64 {
+        self.snapshots.front().map_or(self.frame, |snapshot| snapshot.frame)
+    }
+
+    // going on from an earlier frame replaces what came after it
+    fn forget_future(&mut self) {
+        let frame = self.frame;
+
+        self.snapshots.retain(|snapshot| snapshot.frame <= frame);
+        self.inputs.retain(|(at, _)| *at < frame);
+        self.latest = frame;
+    }
+
+    fn push(&mut self, snapshot: Snapshot) {
+        self.snapshots.push_back(snapshot);
+
+        if self.snapshots.len() > SNAPSHOT_CAPACITY {
+            self.snapshots.pop_front();
+
+            let oldest = self.oldest();
+            self.inputs.retain(|(at, _)| *at >= oldest);
+        }
+    }
+}
+
+// the first snapshot is the machine as it was before anything got logged
+fn begin(uxn: &mut UXN) {
+    if uxn.timeline.snapshots.is_empty() {
+        let snapshot = Snapshot::take(uxn, uxn.timeline.frame);
+        uxn.timeline.push(snapshot);
+    }
+
+    settle(uxn);
+}
+
+// a replay can't make a change done by hand, so once the vector it was done in is over
+// the frames from here on start from a snapshot that has it, along with the inputs before it
+fn settle(uxn: &mut UXN) {
+    if !uxn.timeline.edited || uxn.debugger.is_paused() {
+        return;
+    }
+
+    let frame = uxn.timeline.frame;
+    uxn.timeline.snapshots.retain(|snapshot| snapshot.frame < frame);
+    uxn.timeline.inputs.retain(|(at, _)| *at < frame);
+
+    let snapshot = Snapshot::take(uxn, frame);
+    uxn.timeline.push(snapshot);
+    uxn.timeline.edited = false;
+}
+
+fn apply(uxn: &mut UXN, input: &Input) {
+    match input {
+        Input::MousePos(x, y) => mouse_pos(uxn, *x, *y),
+        Input::MouseDown => mouse_down(uxn),
+        Input::MouseUp => mouse_up(uxn),
+        Input::ConsoleArgs(args) => console_args(uxn, args),
+        Input::Resize(w, h) => screen_resize(uxn, *w, *h),
+    }
+}
+
+// hand an input to the rom, remembering it for replays
+pub fn input(uxn: &mut UXN, input: Input) {
+    // the past can be looked at, not changed
+    if uxn.timeline.scrubbing {
+        return;
+    }
+
+    // a vector held by the debugger would take it in halfway through,
+    // which a replay with the debugger set aside could not do the same way
+    if uxn.debugger.is_paused() {
+        return;
+    }
+
+    begin(uxn);
+
+    if uxn.timeline.frame < uxn.timeline.latest {
+        uxn.timeline.forget_future();
+    }
+
+    uxn.timeline.inputs.push((uxn.timeline.frame, input.clone()));
+    apply(uxn, &input);
+}
+
+// memory or stacks were changed by hand while the debugger holds a vector
+pub fn edit(uxn: &mut UXN) {
+    if uxn.timeline.frame < uxn.timeline.latest {
+        uxn.timeline.forget_future();
+    }
+
+    uxn.timeline.edited = true;
+}
+
+// fire the screen vector, one frame further on the timeline
+pub fn frame(uxn: &mut UXN) {
+    if uxn.timeline.scrubbing || uxn.debugger.is_paused() {
+        return;
+    }
+
+    begin(uxn);
+
+    if uxn.timeline.frame < uxn.timeline.latest {
+        uxn.timeline.forget_future();
+    }
+
+    let screen_vector_addr = uxn.screen.vector();
+    uxn.eval(screen_vector_addr);
+
+    uxn.timeline.frame += 1;
+    uxn.timeline.latest = uxn.timeline.frame;
+
+    // a vector held by the debugger is not a state worth going back to
+    if uxn.timeline.frame % SNAPSHOT_INTERVAL == 0 && !uxn.debugger.is_paused() {
+        let snapshot = Snapshot::take(uxn, uxn.timeline.frame);
+        uxn.timeline.push(snapshot);
+    }
+}
+
+// go to any frame between the oldest snapshot and the latest frame
+pub fn seek(uxn: &mut UXN, target: u64) -> Result<(), String> {
+    // an edit in a vector that is still held goes away with it
+    if uxn.debugger.is_paused() {
+        uxn.timeline.edited = false;
+    }
+
+    settle(uxn);
+
+    let target = target.min(uxn.timeline.latest);
+
+    // the timeline is set aside so the replay does not log itself,
+    // and so are the debugger and the trace so it does not stop or get recorded
+    let timeline = mem::replace(&mut uxn.timeline, Timeline::new());
+    let debugger = mem::replace(&mut uxn.debugger, Debugger::new());
+    let trace = mem::replace(&mut uxn.trace, Trace::new());
+
+    let snapshot = match timeline.snapshots.iter().rev().find(|snapshot| snapshot.frame <= target) {
+        Some(snapshot) => snapshot,
+        None => {
+            uxn.timeline = timeline;
+            uxn.debugger = debugger;
+            uxn.trace = trace;
+            return Err(format!("frame {} is no longer kept", target));
+        }
+    };
+
+    snapshot.restore(uxn);
+
+    let mut inputs = timeline.inputs.iter().skip_while(|(at, _)| *at < snapshot.frame).peekable();
+
+    for frame in snapshot.frame..target {
+        while let Some((_, input)) = inputs.next_if(|(at, _)| *at == frame) {
+            apply(uxn, input);
+        }
+
+        let screen_vector_addr = uxn.screen.vector();
+        uxn.eval(screen_vector_addr);
+    }
+
+    uxn.timeline = timeline;
+    uxn.timeline.frame = target;
+    uxn.debugger = debugger;
+    uxn.trace = trace;
+
+    // a vector stopped by the debugger belongs to the time we left
+    uxn.debugger.pc = None;
+
+    return Ok(());
+}
+
+pub fn step_back(uxn: &mut UXN) -> Result<(), String> {
+    let target = uxn.timeline.frame.saturating_sub(1);
+    seek(uxn, target)?;
+
+    uxn.timeline.scrubbing = true;
+    return Ok(());
+}
+
+pub fn step_forward(uxn: &mut UXN) -> Result<(), String> {
+    let target = uxn.timeline.frame + 1;
+    seek(uxn, target)?;
+
+    uxn.timeline.scrubbing = true;
+    return Ok(());
+}
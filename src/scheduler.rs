@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+// what to do with the frames that were missed while the host was busy
+#[derive(Clone, Copy, PartialEq)]
+pub enum Policy {
+    // run the missed frames back to back, but never more than this many at once
+    CatchUp(u32),
+    // forget about them and carry on from now
+    Drop,
+}
+
+// keeps the screen vector at a fixed rate of wall clock time,
+// independent of how often the editor gets repainted
+pub struct Scheduler {
+    pub period: Duration,
+    pub policy: Policy,
+
+    // when the next frame is due
+    next: Instant,
+
+    // frames that were skipped so far
+    pub dropped: u64,
+}
+
+impl Scheduler {
+    pub fn new(rate: u32, policy: Policy) -> Self {
+        Scheduler {
+            period: Duration::from_secs(1) / rate,
+            policy,
+
+            next: Instant::now(),
+
+            dropped: 0,
+        }
+    }
+
+    // start counting from now, e.g. when the editor opens again
+    pub fn restart(&mut self, now: Instant) {
+        self.next = now;
+    }
+
+    // how many frames have to run at this point in time
+    pub fn due(&mut self, now: Instant) -> u32 {
+        if now < self.next {
+            return 0;
+        }
+
+        let behind = ((now - self.next).as_nanos() / self.period.as_nanos()) as u32 + 1;
+
+        let run = match self.policy {
+            Policy::CatchUp(max) => behind.min(max.max(1)),
+            Policy::Drop => 1,
+        };
+
+        self.dropped += (behind - run) as u64;
+
+        // stay on the same grid, so the rate holds over time
+        self.next += self.period * behind;
+
+        return run;
+    }
+
+    // time left until the next frame
+    pub fn until_next(&self, now: Instant) -> Duration {
+        return self.next.saturating_duration_since(now);
+    }
+}
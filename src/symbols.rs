@@ -0,0 +1,117 @@
+// labels of a rom, as written by the assembler on the .sym file
+//
+// each entry is a 16 bit address followed by a null terminated name
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Default)]
+pub struct SymbolTable {
+    // a label and its sublabels can share the same address
+    names: BTreeMap<u16, Vec<String>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable::default()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        return Ok(SymbolTable::parse(&fs::read(path)?));
+    }
+
+    // the <rom>.sym sitting next to a rom, empty when there is none
+    pub fn for_rom(rom: &Path) -> Self {
+        let mut path = rom.as_os_str().to_owned();
+        path.push(".sym");
+
+        return SymbolTable::load(Path::new(&path)).unwrap_or_default();
+    }
+
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a (u16, String)>) -> Self {
+        let mut table = SymbolTable::new();
+
+        for (addr, name) in entries {
+            table.insert(*addr, name.clone());
+        }
+
+        return table;
+    }
+
+    // a truncated last entry is dropped
+    pub fn parse(data: &[u8]) -> Self {
+        let mut table = SymbolTable::new();
+        let mut i = 0;
+
+        while i + 2 < data.len() {
+            let addr = u16::from_be_bytes([data[i], data[i + 1]]);
+
+            let name = &data[i + 2..];
+            let end = match name.iter().position(|b| *b == 0) {
+                Some(end) => end,
+                None => break,
+            };
+
+            table.insert(addr, String::from_utf8_lossy(&name[..end]).into_owned());
+            i += 2 + end + 1;
+        }
+
+        return table;
+    }
+
+    pub fn insert(&mut self, addr: u16, name: String) {
+        self.names.entry(addr).or_default().push(name);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.values().map(|names| names.len()).sum()
+    }
+
+    // every label at this address, in the order they were defined
+    pub fn names(&self, addr: u16) -> &[String] {
+        match self.names.get(&addr) {
+            Some(names) => names,
+            None => &[],
+        }
+    }
+
+    // the most specific label at this address
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.names(addr).last().map(|name| name.as_str())
+    }
+
+    // address of a label given by its full name
+    pub fn find(&self, name: &str) -> Option<u16> {
+        self.iter().find(|(_, n)| *n == name).map(|(addr, _)| addr)
+    }
+
+    // an address as the closest label before it, like routine/loop+4
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        match self.names.range(..=addr).next_back() {
+            Some((base, names)) if *base == addr => Some(names[names.len() - 1].clone()),
+            // too far away to belong to that label
+            Some((base, names)) if addr - base <= 0xff => Some(format!("{}+{:x}", names[names.len() - 1], addr - base)),
+            _ => None,
+        }
+    }
+
+    // the address in hex, followed by its label when it has one
+    pub fn address(&self, addr: u16) -> String {
+        match self.describe(addr) {
+            Some(label) => format!("{:04x} {}", addr, label),
+            None => format!("{:04x}", addr),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names
+            .iter()
+            .flat_map(|(addr, names)| names.iter().map(move |name| (*addr, name.as_str())))
+    }
+}
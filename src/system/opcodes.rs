@@ -1,3 +1,11 @@
+// mnemonics, in opcode order
+pub const NAMES: [&str; 32] = [
+    "LIT", "INC", "POP", "NIP", "SWP", "ROT", "DUP", "OVR",
+    "EQU", "NEQ", "GTH", "LTH", "JMP", "JCN", "JSR", "STH",
+    "LDZ", "STZ", "LDR", "STR", "LDA", "STA", "DEI", "DEO",
+    "ADD", "SUB", "MUL", "DIV", "AND", "ORA", "EOR", "SFT",
+];
+
 pub enum Opcode {
     // Stack
     LIT = 0x00,
@@ -0,0 +1,395 @@
+// instruction traces, recorded to a ring buffer in memory or to a file
+//
+// every record is RECORD_SIZE bytes:
+//   pc (2) opcode (1) vector (2) wst pointer (1) rst pointer (1) wst top (2) rst top (2)
+// shorts are big endian like everything else on uxn, a file starts with MAGIC
+//
+// a trace recorded with --stacks starts with MAGIC_STACKS instead, and every record is
+// followed by the whole working stack and return stack, as many bytes as their pointers say
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::disassembler::{self, mnemonic};
+use crate::symbols::SymbolTable;
+use crate::system::NAMES;
+use crate::uxn::UXN;
+
+pub const MAGIC: &[u8; 5] = b"AUXT\x01";
+pub const MAGIC_STACKS: &[u8; 5] = b"AUXT\x02";
+pub const RECORD_SIZE: usize = 11;
+
+// instructions shown around a divergence by trace-diff
+pub const DIFF_CONTEXT: usize = 8;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Record {
+    pub pc: u16,
+    pub opcode: u8,
+    // the vector this instruction runs under
+    pub vector: u16,
+
+    pub wst_ptr: u8,
+    pub rst_ptr: u8,
+    // the short at the top of each stack, zero padded when there is not enough on it
+    pub wst_top: u16,
+    pub rst_top: u16,
+    // the whole working and return stacks, bottom first, only with --stacks
+    pub stacks: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+fn stack_top(stack: &[u8]) -> u16 {
+    let ptr = stack[0xff] as usize;
+
+    let hi = if ptr >= 2 { stack[ptr - 2] } else { 0 };
+    let lo = if ptr >= 1 { stack[ptr - 1] } else { 0 };
+
+    return u16::from_be_bytes([hi, lo]);
+}
+
+impl Record {
+    // the state right before the instruction at pc runs
+    pub fn capture(uxn: &UXN, pc: usize, vector: u16) -> Self {
+        let wst = &uxn.ram[uxn.wst..uxn.wst + 0x100];
+        let rst = &uxn.ram[uxn.rst..uxn.rst + 0x100];
+
+        Record {
+            pc: pc as u16,
+            opcode: uxn.ram[pc],
+            vector,
+
+            wst_ptr: wst[0xff],
+            rst_ptr: rst[0xff],
+            wst_top: stack_top(wst),
+            rst_top: stack_top(rst),
+            stacks: if uxn.trace.filter.stacks {
+                Some((wst[..wst[0xff] as usize].to_vec(), rst[..rst[0xff] as usize].to_vec()))
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let [pc_hi, pc_lo] = self.pc.to_be_bytes();
+        let [vec_hi, vec_lo] = self.vector.to_be_bytes();
+        let [wst_hi, wst_lo] = self.wst_top.to_be_bytes();
+        let [rst_hi, rst_lo] = self.rst_top.to_be_bytes();
+
+        [pc_hi, pc_lo, self.opcode, vec_hi, vec_lo, self.wst_ptr, self.rst_ptr, wst_hi, wst_lo, rst_hi, rst_lo]
+    }
+
+    pub fn from_bytes(b: &[u8; RECORD_SIZE]) -> Self {
+        Record {
+            pc: u16::from_be_bytes([b[0], b[1]]),
+            opcode: b[2],
+            vector: u16::from_be_bytes([b[3], b[4]]),
+
+            wst_ptr: b[5],
+            rst_ptr: b[6],
+            wst_top: u16::from_be_bytes([b[7], b[8]]),
+            rst_top: u16::from_be_bytes([b[9], b[10]]),
+            stacks: None,
+        }
+    }
+
+    // one line, with labels when there are any
+    pub fn pretty(&self, symbols: &SymbolTable) -> String {
+        let mut out = format!("{:04x} {:<6}", self.pc, mnemonic(self.opcode));
+
+        if !symbols.is_empty() {
+            out.push_str(&format!(" {:<24}", symbols.describe(self.pc).unwrap_or_default()));
+        }
+
+        out.push_str(&format!(
+            " wst {:02x}:{:04x} rst {:02x}:{:04x} vector {}",
+            self.wst_ptr, self.wst_top, self.rst_ptr, self.rst_top, symbols.address(self.vector)
+        ));
+
+        return out;
+    }
+}
+
+// what gets recorded, everything when left empty
+#[derive(Clone, Default)]
+pub struct Filter {
+    pub range: Option<RangeInclusive<u16>>,
+    pub vector: Option<u16>,
+    // matched without its mode bits, so DEO catches DEO2 and DEOr as well
+    pub opcode: Option<&'static str>,
+    // keep the whole stacks with every record, not just their tops
+    pub stacks: bool,
+}
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).map_err(|_| format!("not a hex number: {}", text))
+}
+
+impl Filter {
+    // --range 0100-01ff --vector 0100 --op DEO --stacks, as given on the command line
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--stacks" {
+                filter.stacks = true;
+                continue;
+            }
+
+            let val = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+
+            match flag.as_str() {
+                "--range" => {
+                    let (start, end) = val.split_once('-').unwrap_or((val, val));
+                    filter.range = Some(parse_hex(start)?..=parse_hex(end)?);
+                }
+                "--vector" => filter.vector = Some(parse_hex(val)?),
+                "--op" => {
+                    let op = NAMES.iter().chain(&["BRK"]).find(|name| **name == val.as_str());
+                    filter.opcode = Some(*op.ok_or_else(|| format!("unknown opcode: {}", val))?);
+                }
+                _ => return Err(format!("unknown filter: {}", flag)),
+            }
+        }
+
+        return Ok(filter);
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        self.range.as_ref().map_or(true, |range| range.contains(&record.pc))
+            && self.vector.map_or(true, |vector| vector == record.vector)
+            && self.opcode.map_or(true, |op| op == disassembler::name(record.opcode))
+    }
+}
+
+pub enum Sink {
+    // only the last records are kept
+    Ring(VecDeque<Record>, usize),
+    File(BufWriter<File>),
+}
+
+pub struct Trace {
+    pub filter: Filter,
+    pub sink: Option<Sink>,
+    // the vector that is running right now
+    pub vector: u16,
+    // recorded since the trace started, filtered ones don't count
+    pub count: u64,
+    // writing to the file failed, the trace stopped there
+    pub error: Option<String>,
+    // what the ring buffer had when it was stopped
+    kept: VecDeque<Record>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace {
+            filter: Filter::default(),
+            sink: None,
+            vector: 0,
+            count: 0,
+            error: None,
+            kept: VecDeque::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    pub fn record_to_ring(&mut self, capacity: usize) {
+        self.kept.clear();
+        self.sink = Some(Sink::Ring(VecDeque::with_capacity(capacity), capacity));
+        self.count = 0;
+        self.error = None;
+    }
+
+    pub fn record_to_file(&mut self, path: &Path) -> io::Result<()> {
+        self.kept.clear();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(if self.filter.stacks { MAGIC_STACKS } else { MAGIC })?;
+
+        self.sink = Some(Sink::File(file));
+        self.count = 0;
+        self.error = None;
+
+        return Ok(());
+    }
+
+    // stops recording, the file gets flushed and the ring buffer can still be looked at
+    pub fn stop(&mut self) -> io::Result<()> {
+        match self.sink.take() {
+            Some(Sink::File(mut file)) => file.flush()?,
+            Some(Sink::Ring(ring, _)) => self.kept = ring,
+            None => {}
+        }
+
+        return Ok(());
+    }
+
+    pub fn push(&mut self, record: Record) {
+        if !self.filter.matches(&record) {
+            return;
+        }
+
+        match &mut self.sink {
+            Some(Sink::Ring(ring, capacity)) => {
+                if ring.len() == *capacity {
+                    ring.pop_front();
+                }
+                ring.push_back(record);
+            }
+
+            Some(Sink::File(file)) => {
+                let written = file.write_all(&record.to_bytes()).and_then(|_| match &record.stacks {
+                    Some((wst, rst)) => file.write_all(wst).and_then(|_| file.write_all(rst)),
+                    None => Ok(()),
+                });
+
+                if let Err(e) = written {
+                    self.error = Some(e.to_string());
+                    self.sink = None;
+                    return;
+                }
+            }
+
+            None => return,
+        }
+
+        self.count += 1;
+    }
+
+    // the last n records the ring buffer holds, oldest first
+    pub fn last(&self, n: usize) -> impl Iterator<Item = &Record> {
+        let records = match &self.sink {
+            Some(Sink::Ring(ring, _)) => ring,
+            _ => &self.kept,
+        };
+
+        return records.range(records.len().saturating_sub(n)..);
+    }
+}
+
+pub fn read_trace(path: &Path) -> io::Result<Vec<Record>> {
+    let mut data = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut data)?;
+
+    let stacks = data.starts_with(MAGIC_STACKS);
+
+    if !stacks && !data.starts_with(MAGIC) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an auxn trace"));
+    }
+
+    let mut records = Vec::new();
+    let mut rest = &data[MAGIC.len()..];
+
+    // a trace cut short keeps every complete record
+    while rest.len() >= RECORD_SIZE {
+        let mut record = Record::from_bytes(rest[..RECORD_SIZE].try_into().unwrap());
+        let mut len = RECORD_SIZE;
+
+        if stacks {
+            len += record.wst_ptr as usize + record.rst_ptr as usize;
+
+            if rest.len() < len {
+                break;
+            }
+
+            let (wst, rst) = rest[RECORD_SIZE..len].split_at(record.wst_ptr as usize);
+            record.stacks = Some((wst.to_vec(), rst.to_vec()));
+        }
+
+        records.push(record);
+        rest = &rest[len..];
+    }
+
+    return Ok(records);
+}
+
+// index of the first record where the two traces part ways,
+// a trace that ends early diverges where it ends
+pub fn first_divergence(a: &[Record], b: &[Record]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| !differences(x, y).is_empty()) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+// the whole stacks are only compared when both traces have them,
+// otherwise a stack differs when its pointer or its top short does
+fn differences(x: &Record, y: &Record) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+
+    let (wst, rst) = match (&x.stacks, &y.stacks) {
+        (Some((x_wst, x_rst)), Some((y_wst, y_rst))) => (x_wst != y_wst, x_rst != y_rst),
+        _ => (false, false),
+    };
+
+    if x.pc != y.pc {
+        fields.push("pc");
+    }
+    if x.opcode != y.opcode {
+        fields.push("opcode");
+    }
+    if x.vector != y.vector {
+        fields.push("vector");
+    }
+    if wst || x.wst_ptr != y.wst_ptr || x.wst_top != y.wst_top {
+        fields.push("wst");
+    }
+    if rst || x.rst_ptr != y.rst_ptr || x.rst_top != y.rst_top {
+        fields.push("rst");
+    }
+
+    return fields;
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+// where two runs stop doing the same thing, with the instructions that led there
+pub fn diff_report(a: &[Record], b: &[Record], context: usize, symbols: &SymbolTable) -> Option<String> {
+    let index = first_divergence(a, b)?;
+    let mut out = format!("traces diverge at instruction {}\n", index);
+
+    for i in index.saturating_sub(context)..index {
+        out.push_str(&format!("    {:>8}  {}\n", i, a[i].pretty(symbols)));
+    }
+
+    for (name, records) in [("a", a), ("b", b)] {
+        match records.get(index) {
+            Some(record) => out.push_str(&format!("{}   {:>8}  {}\n", name, index, record.pretty(symbols))),
+            None => out.push_str(&format!("{}   {:>8}  (trace ends)\n", name, index)),
+        }
+    }
+
+    if let (Some(x), Some(y)) = (a.get(index), b.get(index)) {
+        out.push_str(&format!("differs in {}\n", differences(x, y).join(", ")));
+
+        match (&x.stacks, &y.stacks) {
+            (Some(_), Some(_)) => {
+                for (name, record) in [("a", x), ("b", y)] {
+                    let (wst, rst) = record.stacks.as_ref().unwrap();
+                    out.push_str(&format!("{}   wst [{}] rst [{}]\n", name, hex_bytes(wst), hex_bytes(rst)));
+                }
+            }
+            _ => out.push_str("only the stack pointers and tops were compared, record with --stacks for the whole stacks\n"),
+        }
+    }
+
+    // how each run carried on
+    for (name, records) in [("a", a), ("b", b)] {
+        for i in index + 1..(index + 1 + context).min(records.len()) {
+            out.push_str(&format!("{}   {:>8}  {}\n", name, i, records[i].pretty(symbols)));
+        }
+    }
+
+    return Some(out);
+}
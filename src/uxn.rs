@@ -1,9 +1,11 @@
+use crate::debugger::{Access, Debugger};
 use crate::devices::*;
+use crate::fault::{Error, Fault};
+use crate::rewind::Timeline;
 use crate::system::Opcode;
+use crate::trace::{Record, Trace};
 use std::sync::mpsc;
 
-use nih_plug_egui::egui::{Color32, ColorImage};
-
 const MAX_INSTR: u8 = 0x1f;
 
 pub struct UXN {
@@ -30,10 +32,17 @@ pub struct UXN {
     pub halted: bool,
     pub limit: u64,
 
+    // raised by the instruction being run, it becomes a fault once the instruction is done
+    pub error: Option<Error>,
+
     pub system: SystemDevice,
     pub console: ConsoleDevice,
     pub screen: ScreenDevice,
     pub mouse: MouseDevice,
+
+    pub debugger: Debugger,
+    pub trace: Trace,
+    pub timeline: Timeline,
 }
 
 impl UXN {
@@ -63,16 +72,27 @@ impl UXN {
             halted: false,
             limit: 0x40000,
 
+            error: None,
+
             system: SystemDevice::new(),
             console: ConsoleDevice::new(),
             screen: ScreenDevice::new(w, h),
             mouse: MouseDevice::new(),
+
+            debugger: Debugger::new(),
+            trace: Trace::new(),
+            timeline: Timeline::new(),
         }
     }
 
+    // roms past the end of the main memory carry on into the expansion banks,
+    // what doesn't fit in those is dropped
     pub fn load(&mut self, program: Vec<u8>) {
-        for (i, instr) in program.iter().enumerate() {
-            self.ram[0x100 + i] = *instr;
+        let (main, rest) = program.split_at(program.len().min(0x10000 - 0x100));
+        self.ram[0x100..0x100 + main.len()].copy_from_slice(main);
+
+        for (i, chunk) in rest.chunks(0x10000).take(BANKS - 1).enumerate() {
+            self.system.bank(i + 1)[..chunk.len()].copy_from_slice(chunk);
         }
     }
 
@@ -134,14 +154,6 @@ impl UXN {
         if val >= min { if val <= max { val } else { max } } else { min }
     }
 
-    pub fn bg_color(&mut self) {
-        for p in &mut self.screen.bg.pixels {
-            if *p == Color32::TRANSPARENT {
-                *p = self.screen.color0;
-            }
-        }
-    }
-
     // move to external?
     fn interrupt(&self) -> u8 {
         return 1;
@@ -175,21 +187,54 @@ impl UXN {
         self.c = 0;
     }
 
-    pub fn eval(&mut self, mut pc: usize) {
-        if pc == 0 || self.dev_get(0xf) != 0 {
+    pub fn eval(&mut self, pc: usize) {
+        if pc == 0 || self.system.exit.is_some() || self.system.fault.is_some() {
+            return;
+        }
+
+        // a paused vector has to finish before another one can start
+        if self.debugger.is_paused() {
             return;
         }
 
+        self.trace.vector = pc as u16;
+        self.run(pc);
+    }
+
+    // pick up the vector the debugger stopped
+    pub fn resume(&mut self) {
+        if let Some(pc) = self.debugger.pc.take() {
+            self.run(pc);
+        }
+
+        // console input that came in while the vector was held
+        console_flush(self);
+    }
+
+    fn run(&mut self, mut pc: usize) {
         while !self.halted {
+            if !self.debugger.is_idle() {
+                let depth = self.ram[self.rst + 0xff];
+
+                if self.debugger.should_break(pc, depth) {
+                    return;
+                }
+            }
+
+            if self.trace.is_recording() {
+                let record = Record::capture(self, pc, self.trace.vector);
+                self.trace.push(record);
+            }
+
             pc = self.step(pc);
         }
 
+        self.debugger.vector_done();
         self.reset();
     }
 
     pub fn step(&mut self, mut pc: usize) -> usize {
-        let debug = false;
-
+        let at = pc;
         let instr = self.ram[pc];
         pc = pc + 1;
 
@@ -233,47 +278,27 @@ impl UXN {
 
         match Opcode::try_from(instr & MAX_INSTR) {
             Ok(Opcode::LIT) => {
-                if debug {
-                    println!("-> LIT - r2: {}", self.r2);
-                }
-
                 self.PUSH(self.PEEK(pc));
                 pc = pc + (1 + self.bs);
             }
 
             Ok(Opcode::INC) => {
-                if debug {
-                    println!("-> INC - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
 
                 self.PUSH(self.a.wrapping_add(1).into());
             }
 
             Ok(Opcode::POP) => {
-                if debug {
-                    println!("-> POP - r2: {}", self.r2);
-                }
-
                 self.POP();
             }
 
             Ok(Opcode::NIP) => {
-                if debug {
-                    println!("-> NIP - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.POP();
                 self.PUSH(self.a.into());
             }
 
             Ok(Opcode::SWP) => {
-                if debug {
-                    println!("-> SWP - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.a);
@@ -281,10 +306,6 @@ impl UXN {
             }
 
             Ok(Opcode::ROT) => {
-                if debug {
-                    println!("-> ROT - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.c = self.POP();
@@ -294,20 +315,12 @@ impl UXN {
             }
 
             Ok(Opcode::DUP) => {
-                if debug {
-                    println!("-> DUP - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.PUSH(self.a);
                 self.PUSH(self.a);
             }
 
             Ok(Opcode::OVR) => {
-                if debug {
-                    println!("-> OVR - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b);
@@ -316,10 +329,6 @@ impl UXN {
             }
 
             Ok(Opcode::EQU) => {
-                if debug {
-                    println!("-> EQU - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 if self.b == self.a {
@@ -330,10 +339,6 @@ impl UXN {
             }
 
             Ok(Opcode::NEQ) => {
-                if debug {
-                    println!("-> NEQ - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 if self.b != self.a {
@@ -344,10 +349,6 @@ impl UXN {
             }
 
             Ok(Opcode::GTH) => {
-                if debug {
-                    println!("-> GTH - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 if self.b > self.a {
@@ -358,10 +359,6 @@ impl UXN {
             }
 
             Ok(Opcode::LTH) => {
-                if debug {
-                    println!("-> LTH - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 if self.b < self.a {
@@ -372,19 +369,11 @@ impl UXN {
             }
 
             Ok(Opcode::JMP) => {
-                if debug {
-                    println!("-> JMP - r2: {}", self.r2);
-                }
-
                 self.a = self.POP().into();
                 pc = self.JUMP(self.a.into(), pc);
             }
 
             Ok(Opcode::JCN) => {
-                if debug {
-                    println!("-> JCN - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 if self.POP8() != 0 {
                     pc = self.JUMP(self.a.into(), pc);
@@ -392,20 +381,12 @@ impl UXN {
             }
 
             Ok(Opcode::JSR) => {
-                if debug {
-                    println!("-> JSR - r2: {}", self.r2);
-                }
-
                 self.DST_PUSH16(pc.try_into().unwrap());
                 self.a = self.POP().into();
                 pc = self.JUMP(self.a.into(), pc);
             }
 
             Ok(Opcode::STH) => {
-                if debug {
-                    println!("-> STH - r2: {}", self.r2);
-                }
-
                 if self.r2 {
                     self.a = self.POP16();
                     self.DST_PUSH16(self.a);
@@ -416,171 +397,124 @@ impl UXN {
             }
 
             Ok(Opcode::LDZ) => {
-                if debug {
-                    println!("-> LDZ - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
-                self.PUSH(self.PEEK(self.a.into()));
+                self.c = self.PEEK(self.a.into());
+
+                self.debugger.check_ram(Access::Read, self.a, self.r2, self.c, pc - 1);
+                self.PUSH(self.c);
             }
 
             Ok(Opcode::STZ) => {
-                if debug {
-                    println!("-> STZ - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
                 self.b = self.POP();
 
+                self.debugger.check_ram(Access::Write, self.a, self.r2, self.b, pc - 1);
                 self.POKE(self.a.into(), self.b);
             }
 
             Ok(Opcode::LDR) => {
-                if debug {
-                    println!("-> LDR - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
 
-                self.PUSH(self.PEEK(pc.wrapping_add(self.rel(self.a.into()))));
+                let addr = pc.wrapping_add(self.rel(self.a.into()));
+                self.c = self.PEEK(addr);
+
+                self.debugger.check_ram(Access::Read, addr as u16, self.r2, self.c, pc - 1);
+                self.PUSH(self.c);
             }
 
             Ok(Opcode::STR) => {
-                if debug {
-                    println!("-> STR - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
                 self.b = self.POP();
 
-                self.POKE(pc.wrapping_add(self.rel(self.a.into())), self.b);
+                let addr = pc.wrapping_add(self.rel(self.a.into()));
+
+                self.debugger.check_ram(Access::Write, addr as u16, self.r2, self.b, pc - 1);
+                self.POKE(addr, self.b);
             }
 
             Ok(Opcode::LDA) => {
-                if debug {
-                    println!("-> LDA - r2: {}", self.r2);
-                }
-
                 self.a = self.POP16();
+                self.c = self.PEEK(self.a.into());
 
-                self.PUSH(self.PEEK(self.a.into()));
+                self.debugger.check_ram(Access::Read, self.a, self.r2, self.c, pc - 1);
+                self.PUSH(self.c);
             }
 
             Ok(Opcode::STA) => {
-                if debug {
-                    println!("-> STA - r2: {}", self.r2);
-                }
-
                 self.a = self.POP16();
                 self.b = self.POP();
 
+                self.debugger.check_ram(Access::Write, self.a, self.r2, self.b, pc - 1);
                 self.POKE(self.a.into(), self.b);
             }
 
             Ok(Opcode::DEI) => {
-                if debug {
-                    println!("-> DEI - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
+                self.c = self.DEVR(self.a.into()).into();
 
-                self.PUSH(self.DEVR(self.a.into()).into());
+                self.debugger.check_port(Access::Read, self.a as u8, self.r2, self.c, pc - 1);
+                self.PUSH(self.c);
             }
 
             Ok(Opcode::DEO) => {
-                if debug {
-                    println!("-> DEO - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
                 self.b = self.POP();
 
                 // println!("DEO VALUE: {:?} - r2: {}", y, self.r2);
                 // println!("------------------");
 
+                self.debugger.check_port(Access::Write, self.a as u8, self.r2, self.b, pc - 1);
                 self.DEVW(self.a.into(), self.b);
             }
 
             Ok(Opcode::ADD) => {
-                if debug {
-                    println!("-> ADD - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b.wrapping_add(self.a));
             }
 
             Ok(Opcode::SUB) => {
-                if debug {
-                    println!("-> SUB - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b.wrapping_sub(self.a));
             }
 
             Ok(Opcode::MUL) => {
-                if debug {
-                    println!("-> MUL - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b.wrapping_mul(self.a));
             }
 
             Ok(Opcode::DIV) => {
-                if debug {
-                    println!("-> DIV - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
 
                 if self.a == 0 {
-                    panic!("IMPOSIBLE DIVISION");
+                    self.raise(Error::DivisionByZero);
+                } else {
+                    self.PUSH(self.b.wrapping_div(self.a));
                 }
-
-                self.PUSH(self.b.wrapping_div(self.a));
             }
 
             Ok(Opcode::AND) => {
-                if debug {
-                    println!("-> AND - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b & self.a);
             }
 
             Ok(Opcode::ORA) => {
-                if debug {
-                    println!("-> ORA - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b | self.a);
             }
 
             Ok(Opcode::EOR) => {
-                if debug {
-                    println!("-> EOR - r2: {}", self.r2);
-                }
-
                 self.a = self.POP();
                 self.b = self.POP();
                 self.PUSH(self.b ^ self.a);
             }
 
             Ok(Opcode::SFT) => {
-                if debug {
-                    println!("-> SFT - r2: {}", self.r2);
-                }
-
                 self.a = self.POP8().into();
                 self.b = self.POP();
 
@@ -588,10 +522,16 @@ impl UXN {
             }
 
             Err(_) => {
-                panic!("unknown instruction :(")
+                self.raise(Error::UnknownInstruction(instr));
             }
         }
 
+        // the machine stops right there, with the stacks as the instruction left them
+        if let Some(error) = self.error.take() {
+            self.system.fault = Some(Fault { error, pc: at as u16 });
+            self.halted = true;
+        }
+
         // println!("ptr: {}", self.ptr());
 
         // let wst = &self.ram[self.wst..self.wst+20];
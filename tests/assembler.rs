@@ -0,0 +1,88 @@
+// the assembler against the prebuilt tests.rom, and the errors it has to catch
+// instead of writing garbage
+
+use auxn::assembler::{self, AsmError};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn repo(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(name);
+}
+
+fn error(source: &str) -> AsmError {
+    match assembler::assemble(source, Path::new("test.tal")) {
+        Ok(_) => panic!("expected {:?} to fail", source),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn tests_tal_matches_tests_rom() {
+    let assembly = assembler::assemble_file(&repo("tests.tal")).unwrap();
+
+    assert!(assembly.rom == fs::read(repo("tests.rom")).unwrap(), "tests.tal does not assemble to tests.rom");
+    assert!(assembly.sym() == fs::read(repo("tests.rom.sym")).unwrap(), "tests.tal does not assemble to tests.rom.sym");
+}
+
+#[test]
+fn padding_past_the_address_space() {
+    let e = error("|0100 $ffffffffffffffff #01");
+
+    assert_eq!(e.message, "Invalid padding: $ffffffffffffffff");
+}
+
+#[test]
+fn writing_past_the_end_of_memory() {
+    let e = error("|ffff #01");
+
+    assert_eq!(e.message, "Writing outside of memory: #01");
+}
+
+#[test]
+fn zero_page_reference_to_a_far_label() {
+    let e = error("|0100 .far BRK\n|0200 @far");
+
+    assert_eq!(e.line, 1);
+    assert_eq!(e.message, "Address is not in zero-page: .far");
+}
+
+#[test]
+fn going_back_keeps_what_was_written_further_up() {
+    let assembly = assembler::assemble("|0200 #01 |0100 #02", Path::new("test.tal")).unwrap();
+
+    assert_eq!(assembly.rom.len(), 0x102);
+    assert_eq!(&assembly.rom[..2], &[0x80, 0x02]);
+    assert_eq!(&assembly.rom[0x100..], &[0x80, 0x01]);
+}
+
+// a fresh directory for the files of one test
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("auxn-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    return dir;
+}
+
+#[test]
+fn includes() {
+    let dir = scratch("include");
+    fs::write(dir.join("main.tal"), "|0100 ;lib/routine JSR2 BRK\n~lib.tal").unwrap();
+    fs::write(dir.join("lib.tal"), "@lib &routine #2a JMP2r").unwrap();
+
+    let assembly = assembler::assemble_file(&dir.join("main.tal")).unwrap();
+
+    // the include lands where it is written, and its labels are known before it
+    assert_eq!(assembly.rom, [0xa0, 0x01, 0x05, 0x2e, 0x00, 0x80, 0x2a, 0x6c]);
+
+    // errors point at the included file
+    fs::write(dir.join("lib.tal"), "\n#2a FOO").unwrap();
+    let e = assembler::assemble_file(&dir.join("main.tal")).err().unwrap();
+    assert_eq!((e.file, e.line), (dir.join("lib.tal"), 2));
+
+    fs::write(dir.join("lib.tal"), "~lib.tal").unwrap();
+    let e = assembler::assemble_file(&dir.join("main.tal")).err().unwrap();
+    assert!(e.message.starts_with("Includes nested too deep"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,85 @@
+// command blocks sent through the System expansion port, and roms too big for the main memory
+
+use auxn::assembler;
+use auxn::devices::BANKS;
+use auxn::uxn::UXN;
+
+use std::path::Path;
+
+// runs the command blocks one after the other, the data to work on sits at 0300
+fn expansion(blocks: &[&str]) -> UXN {
+    let mut send = String::new();
+    let mut cmds = String::new();
+
+    for (i, block) in blocks.iter().enumerate() {
+        send.push_str(&format!(";cmd{} .System/expansion DEO2 ", i));
+        cmds.push_str(&format!("@cmd{} {} ", i, block));
+    }
+
+    let source = format!(
+        "|00 @System &vector $2 &expansion $2
+        |0100 {} BRK {}
+        |0300 01 02 03 04 05",
+        send, cmds
+    );
+    let assembly = assembler::assemble(&source, Path::new("expansion.tal")).unwrap();
+
+    let mut uxn = UXN::new(64, 64);
+    uxn.load(assembly.rom);
+    uxn.eval(0x100);
+
+    return uxn;
+}
+
+#[test]
+fn fill() {
+    // length 3, bank 0, at 0301, with aa
+    let uxn = expansion(&["00 0003 0000 0301 aa"]);
+
+    assert_eq!(uxn.ram[0x300..0x305], [0x01, 0xaa, 0xaa, 0xaa, 0x05]);
+}
+
+#[test]
+fn overlapping_copy_left() {
+    // length 4, from bank 0 0301 to bank 0 0300
+    let uxn = expansion(&["01 0004 0000 0301 0000 0300"]);
+
+    assert_eq!(uxn.ram[0x300..0x305], [0x02, 0x03, 0x04, 0x05, 0x05]);
+}
+
+#[test]
+fn overlapping_copy_right() {
+    // length 4, from bank 0 0300 to bank 0 0301
+    let uxn = expansion(&["02 0004 0000 0300 0000 0301"]);
+
+    assert_eq!(uxn.ram[0x300..0x305], [0x01, 0x01, 0x02, 0x03, 0x04]);
+}
+
+#[test]
+fn round_trip_through_a_bank() {
+    // out to the end of bank 2, given as 0x12 so it wraps, then back in at 0400
+    let uxn = expansion(&["01 0005 0000 0300 0012 fffe", "01 0005 0002 fffe 0000 0400"]);
+
+    // the copy wrapped around to the start of the bank
+    assert_eq!(uxn.system.banks[1][0xfffe..], [0x01, 0x02]);
+    assert_eq!(uxn.system.banks[1][..3], [0x03, 0x04, 0x05]);
+
+    assert_eq!(uxn.ram[0x400..0x405], [0x01, 0x02, 0x03, 0x04, 0x05]);
+}
+
+#[test]
+fn big_roms_carry_on_into_the_banks() {
+    let mut rom = vec![0x00; 0xff00];
+    rom.extend([0x11, 0x22]);
+    rom.extend(vec![0x33; 0x10000 * BANKS]);
+
+    let mut uxn = UXN::new(64, 64);
+    uxn.load(rom);
+
+    // the stacks and devices right after the main memory are left alone
+    assert!(uxn.ram[0x10000..].iter().all(|b| *b == 0));
+
+    assert_eq!(uxn.system.banks.len(), BANKS - 1);
+    assert_eq!(uxn.system.banks[0][..3], [0x11, 0x22, 0x33]);
+    assert!(uxn.system.banks[BANKS - 2].iter().all(|b| *b == 0x33));
+}
@@ -0,0 +1,76 @@
+// stack errors and division by zero stop the machine on the instruction
+// that caused them, instead of panicking
+
+use auxn::assembler;
+use auxn::fault::{Error, Fault, Stack};
+use auxn::symbols::SymbolTable;
+use auxn::uxn::UXN;
+
+use std::path::Path;
+
+fn run(source: &str) -> (UXN, SymbolTable) {
+    let assembly = assembler::assemble(source, Path::new("fault.tal")).unwrap();
+
+    let mut uxn = UXN::new(64, 64);
+    uxn.load(assembly.rom);
+    uxn.eval(0x100);
+
+    return (uxn, SymbolTable::from_entries(&assembly.symbols));
+}
+
+#[test]
+fn working_stack_underflow() {
+    let (uxn, symbols) = run("|0100 #01 POP @broken POP BRK");
+    let fault = uxn.system.fault.unwrap();
+
+    assert_eq!(fault, Fault { error: Error::Underflow(Stack::Working), pc: 0x0103 });
+    assert_eq!(fault.report(&symbols), "working stack underflow at 0103 broken");
+    assert_eq!(fault.report(&SymbolTable::new()), "working stack underflow at 0103");
+}
+
+#[test]
+fn keep_mode_underflow() {
+    let (uxn, _) = run("|0100 #01 ADDk BRK");
+
+    assert_eq!(uxn.system.fault.unwrap().error, Error::Underflow(Stack::Working));
+}
+
+#[test]
+fn return_stack_underflow() {
+    let (uxn, symbols) = run("|0100 @main JMP2r");
+    let fault = uxn.system.fault.unwrap();
+
+    assert_eq!(fault.error, Error::Underflow(Stack::Return));
+    assert_eq!(fault.report(&symbols), "return stack underflow at 0100 main");
+}
+
+#[test]
+fn working_stack_overflow() {
+    let (uxn, symbols) = run("|0100 @loop #01 ,loop JMP");
+    let fault = uxn.system.fault.unwrap();
+
+    assert_eq!(fault.error, Error::Overflow(Stack::Working));
+    assert_eq!(fault.report(&symbols), "working stack overflow at 0102 loop+2");
+}
+
+#[test]
+fn return_stack_overflow() {
+    let (uxn, _) = run("|0100 @loop #01 STH ,loop JMP");
+
+    assert_eq!(uxn.system.fault.unwrap().error, Error::Overflow(Stack::Return));
+}
+
+#[test]
+fn division_by_zero() {
+    let (uxn, symbols) = run("|0100 #01 #00 @divide DIV BRK");
+
+    assert_eq!(uxn.system.fault.unwrap().report(&symbols), "division by zero at 0104 divide");
+}
+
+#[test]
+fn a_faulted_machine_runs_no_more_vectors() {
+    let (mut uxn, _) = run("|0100 POP BRK |0200 #2a #00 STZ BRK");
+
+    uxn.eval(0x200);
+    assert_eq!(uxn.ram[0x00], 0);
+}
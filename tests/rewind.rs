@@ -0,0 +1,192 @@
+// going back in time has to land on exactly the machine that was there,
+// checked against the ram of a live run fed with console and mouse input
+
+use auxn::assembler;
+use auxn::debugger;
+use auxn::rewind::{self, Input, SNAPSHOT_CAPACITY, SNAPSHOT_INTERVAL};
+use auxn::uxn::UXN;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+// counts frames, adds up console input and mouse clicks, keeps the last mouse x
+const ROM: &str = "
+|10 @Console &vector $2 &read $1 &pad $4 &type $1 &write $1
+|20 @Screen &vector $2 &width $2 &height $2 &auto $1 &pad $1 &x $2 &y $2 &addr $2 &pixel $1
+|90 @Mouse &vector $2 &x $2 &y $2 &state $1
+|0000 @count $2 @acc $1 @clicks $1 @pos $2
+|0100
+	;on-frame .Screen/vector DEO2
+	;on-console .Console/vector DEO2
+	;on-mouse .Mouse/vector DEO2
+BRK
+@on-frame
+	.count LDZ2 INC2 .count STZ2
+	.count LDZ2 #003f AND2 .Screen/x DEO2 #0001 .Screen/y DEO2 #01 .Screen/pixel DEO
+BRK
+@on-console
+	.Console/read DEI .acc LDZ ADD .acc STZ
+BRK
+@on-mouse
+	.Mouse/x DEI2 .pos STZ2
+	.Mouse/state DEI .clicks LDZ ADD .clicks STZ
+BRK
+";
+
+fn boot() -> UXN {
+    let assembly = assembler::assemble(ROM, Path::new("rewind.tal")).unwrap();
+
+    let mut uxn = UXN::new(64, 64);
+    uxn.load(assembly.rom);
+    uxn.eval(0x100);
+
+    return uxn;
+}
+
+fn digest(uxn: &UXN) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    uxn.ram.hash(&mut hasher);
+
+    return hasher.finish();
+}
+
+// runs the given number of frames with some input along the way,
+// returns the ram as it was at the start of every frame and once they all ran
+fn record(uxn: &mut UXN, frames: u64) -> Vec<u64> {
+    let mut live = Vec::new();
+
+    for frame in 0..frames {
+        live.push(digest(uxn));
+
+        if frame % 7 == 3 {
+            rewind::input(uxn, Input::ConsoleArgs(vec![format!("{}", frame)]));
+        }
+
+        if frame % 11 == 5 {
+            rewind::input(uxn, Input::MousePos(frame as f32 % 64.0, 2.0));
+            rewind::input(uxn, Input::MouseDown);
+            rewind::input(uxn, Input::MouseUp);
+        }
+
+        rewind::frame(uxn);
+    }
+
+    live.push(digest(uxn));
+
+    return live;
+}
+
+#[test]
+fn seek_reproduces_the_live_run() {
+    let mut uxn = boot();
+    let live = record(&mut uxn, 200);
+
+    for k in [0, 1, 59, 60, 61, 75, 120, 150, 199, 200] {
+        rewind::seek(&mut uxn, k).unwrap();
+        assert_eq!(digest(&uxn), live[k as usize], "frame {}", k);
+    }
+
+    // and back again, in the other direction
+    for k in [200, 42, 0] {
+        rewind::seek(&mut uxn, k).unwrap();
+        assert_eq!(digest(&uxn), live[k as usize], "frame {}", k);
+    }
+}
+
+#[test]
+fn seek_after_old_snapshots_are_dropped() {
+    let frames = SNAPSHOT_INTERVAL * (SNAPSHOT_CAPACITY as u64 + 3) + 17;
+
+    let mut uxn = boot();
+    let live = record(&mut uxn, frames);
+
+    let oldest = uxn.timeline.oldest();
+    assert!(oldest > 0);
+    assert!(rewind::seek(&mut uxn, oldest - 1).is_err());
+
+    for k in [oldest, oldest + 1, oldest + SNAPSHOT_INTERVAL + 9, frames - 1, frames] {
+        rewind::seek(&mut uxn, k).unwrap();
+        assert_eq!(digest(&uxn), live[k as usize], "frame {}", k);
+    }
+}
+
+#[test]
+fn going_on_from_an_earlier_frame() {
+    let mut uxn = boot();
+    record(&mut uxn, 90);
+
+    // what runs from frame 30 on replaces frames 30 to 90
+    rewind::seek(&mut uxn, 30).unwrap();
+    let live = record(&mut uxn, 50);
+
+    assert_eq!(uxn.timeline.latest, 80);
+
+    rewind::seek(&mut uxn, 65).unwrap();
+    assert_eq!(digest(&uxn), live[35]);
+}
+
+#[test]
+fn step_back_past_the_oldest_frame() {
+    let mut uxn = boot();
+
+    assert!(rewind::step_back(&mut uxn).is_err());
+    assert!(!uxn.timeline.scrubbing);
+
+    record(&mut uxn, 10);
+
+    rewind::step_back(&mut uxn).unwrap();
+    assert!(uxn.timeline.scrubbing);
+    assert_eq!(uxn.timeline.frame, 9);
+}
+
+#[test]
+fn input_while_the_debugger_holds_a_vector() {
+    let mut uxn = boot();
+    record(&mut uxn, 10);
+
+    // stops on the first instruction of the next screen vector
+    debugger::debug_pause(&mut uxn, "test");
+    rewind::frame(&mut uxn);
+    assert!(uxn.debugger.is_paused());
+
+    // never reaches the rom, so it can't be missing from a replay either
+    let held = digest(&uxn);
+    rewind::input(&mut uxn, Input::MousePos(9.0, 9.0));
+    rewind::input(&mut uxn, Input::MouseDown);
+    assert_eq!(digest(&uxn), held);
+
+    debugger::debug_continue(&mut uxn);
+    let live = record(&mut uxn, 5);
+
+    rewind::seek(&mut uxn, 11).unwrap();
+    assert_eq!(digest(&uxn), live[0]);
+}
+
+#[test]
+fn edit_while_the_debugger_holds_a_vector() {
+    let mut uxn = boot();
+    let before = record(&mut uxn, 10);
+
+    debugger::debug_pause(&mut uxn, "test");
+    rewind::frame(&mut uxn);
+    assert!(uxn.debugger.is_paused());
+
+    // patch the console total by hand halfway through the screen vector
+    uxn.ram[0x02] = 0x40;
+    rewind::edit(&mut uxn);
+
+    debugger::debug_continue(&mut uxn);
+    let live = record(&mut uxn, 70);
+
+    // the frames after the edit have it, the ones before don't
+    for k in [11, 12, 40, 80] {
+        rewind::seek(&mut uxn, k).unwrap();
+        assert_eq!(digest(&uxn), live[k as usize - 11], "frame {}", k);
+    }
+
+    for k in [0, 5, 10] {
+        rewind::seek(&mut uxn, k).unwrap();
+        assert_eq!(digest(&uxn), before[k as usize], "frame {}", k);
+    }
+}
@@ -0,0 +1,72 @@
+// the screen vector rate, with the clock handed in so stalls can be made up
+
+use auxn::scheduler::{Policy, Scheduler};
+
+use std::time::{Duration, Instant};
+
+fn scheduler(policy: Policy, start: Instant) -> Scheduler {
+    let mut scheduler = Scheduler::new(60, policy);
+    scheduler.restart(start);
+
+    return scheduler;
+}
+
+#[test]
+fn on_time() {
+    let start = Instant::now();
+    let mut scheduler = scheduler(Policy::CatchUp(4), start);
+    let period = scheduler.period;
+
+    assert_eq!(scheduler.due(start), 1);
+    assert_eq!(scheduler.due(start + period / 2), 0);
+    assert_eq!(scheduler.due(start + period), 1);
+    assert_eq!(scheduler.dropped, 0);
+}
+
+#[test]
+fn catch_up_runs_at_most_four_frames() {
+    let start = Instant::now();
+    let mut scheduler = scheduler(Policy::CatchUp(4), start);
+    let period = scheduler.period;
+
+    // three frames late, all of them run
+    assert_eq!(scheduler.due(start + period * 2), 3);
+    assert_eq!(scheduler.dropped, 0);
+
+    // ten frames late, four run and the rest are gone
+    assert_eq!(scheduler.due(start + period * 12), 4);
+    assert_eq!(scheduler.dropped, 6);
+
+    // and it carries on from there
+    assert_eq!(scheduler.due(start + period * 12), 0);
+    assert_eq!(scheduler.due(start + period * 13), 1);
+}
+
+#[test]
+fn drop_skips_the_missed_frames() {
+    let start = Instant::now();
+    let mut scheduler = scheduler(Policy::Drop, start);
+    let period = scheduler.period;
+
+    assert_eq!(scheduler.due(start + period * 9), 1);
+    assert_eq!(scheduler.dropped, 9);
+
+    assert_eq!(scheduler.due(start + period * 9), 0);
+    assert_eq!(scheduler.due(start + period * 10), 1);
+    assert_eq!(scheduler.dropped, 9);
+}
+
+#[test]
+fn until_next_after_a_stall() {
+    let start = Instant::now();
+    let mut scheduler = scheduler(Policy::CatchUp(4), start);
+    let period = scheduler.period;
+
+    // nothing ran for a while, the next frame is already late
+    let stalled = start + period * 5 + period / 4;
+    assert_eq!(scheduler.until_next(stalled), Duration::ZERO);
+
+    // once the missed frames ran, the next one stays on the grid
+    scheduler.due(stalled);
+    assert_eq!(scheduler.until_next(stalled), period - period / 4);
+}
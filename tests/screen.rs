@@ -0,0 +1,159 @@
+// golden image tests for the screen device, every test assembles a small rom by hand,
+// runs it headless and compares the screen with tests/screen/<name>.png
+//
+// AUXN_BLESS=1 cargo test --test screen   rewrites the references
+
+use auxn::headless;
+
+use std::path::PathBuf;
+
+const LIT: u8 = 0x80;
+const LIT2: u8 = 0xa0;
+const DEO: u8 = 0x17;
+const DEO2: u8 = 0x37;
+const BRK: u8 = 0x00;
+
+// where the sprites live in memory
+const SPRITES: u16 = 0x0800;
+
+const ONE_BPP: [u8; 8] = [0x3c, 0x42, 0x81, 0xa5, 0x81, 0x99, 0x42, 0x3c];
+const TWO_BPP: [u8; 16] = [
+    0x00, 0x3c, 0x42, 0x5a, 0x5a, 0x42, 0x3c, 0x00,
+    0xff, 0xc3, 0x81, 0x81, 0x81, 0x81, 0xc3, 0xff,
+];
+
+struct Rom {
+    program: Vec<u8>,
+    data: Vec<(u16, Vec<u8>)>,
+}
+
+impl Rom {
+    fn new() -> Self {
+        let mut rom = Rom {
+            program: Vec::new(),
+            data: vec![(SPRITES, ONE_BPP.to_vec()), (SPRITES + 8, TWO_BPP.to_vec())],
+        };
+
+        // the palette from the varvara documentation
+        rom.deo2(0x08, 0x0f7f);
+        rom.deo2(0x0a, 0x0fd6);
+        rom.deo2(0x0c, 0x0fb2);
+
+        return rom;
+    }
+
+    fn deo(&mut self, port: u8, val: u8) {
+        self.program.extend([LIT, val, LIT, port, DEO]);
+    }
+
+    fn deo2(&mut self, port: u8, val: u16) {
+        self.program.extend([LIT2, (val >> 8) as u8, val as u8, LIT, port, DEO2]);
+    }
+
+    // put data at an absolute address, after the program
+    fn data(&mut self, addr: u16, data: &[u8]) {
+        self.data.push((addr, data.to_vec()));
+    }
+
+    fn sprite(&mut self, x: u16, y: u16, addr: u16, ctrl: u8) {
+        self.deo2(0x28, x);
+        self.deo2(0x2a, y);
+        self.deo2(0x2c, addr);
+        self.deo(0x2f, ctrl);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut bytes = self.program;
+        bytes.push(BRK);
+
+        let mut data = self.data;
+        data.sort_by_key(|(addr, _)| *addr);
+
+        for (addr, data) in data {
+            let offset = (addr - 0x100) as usize;
+            assert!(bytes.len() <= offset, "rom program runs into the data at {:04x}", addr);
+
+            bytes.resize(offset, 0);
+            bytes.extend(data);
+        }
+
+        return bytes;
+    }
+}
+
+fn reference(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/screen").join(format!("{}.png", name));
+}
+
+fn check(name: &str, rom: Vec<u8>, width: u32, height: u32, frames: usize) {
+    let uxn = headless::run(rom, width, height, frames);
+
+    if let Err(diff) = headless::compare(&uxn, &reference(name)) {
+        panic!("\n{}\n", diff);
+    }
+}
+
+#[test]
+fn sprite_blending() {
+    let mut rom = Rom::new();
+
+    // every blending mode, 1bpp and 2bpp, on both layers, flipped on the foreground
+    for color in 0..16u16 {
+        let x = color * 10;
+
+        rom.sprite(x, 0, SPRITES, color as u8);
+        rom.sprite(x, 10, SPRITES, 0x40 | 0x10 | color as u8);
+        rom.sprite(x, 20, SPRITES + 8, 0x80 | color as u8);
+        rom.sprite(x, 30, SPRITES + 8, 0x80 | 0x40 | 0x20 | color as u8);
+    }
+
+    check("sprite_blending", rom.finish(), 160, 40, 0);
+}
+
+#[test]
+fn foreground_over_background() {
+    let mut rom = Rom::new();
+
+    // a background sprite half covered by a foreground one,
+    // the foreground color 0 has to let the background through
+    rom.sprite(4, 4, SPRITES + 8, 0x80 | 0x05);
+    rom.sprite(8, 4, SPRITES + 8, 0x80 | 0x40 | 0x0a);
+
+    check("foreground_over_background", rom.finish(), 24, 16, 0);
+}
+
+#[test]
+fn pixel_fill() {
+    let mut rom = Rom::new();
+
+    // one quadrant per flip combination, from the center of the screen
+    rom.deo2(0x28, 16);
+    rom.deo2(0x2a, 12);
+    rom.deo(0x2e, 0x80 | 0x01);
+    rom.deo(0x2e, 0x80 | 0x10 | 0x02);
+    rom.deo(0x2e, 0x80 | 0x20 | 0x03);
+    rom.deo(0x2e, 0x80 | 0x40 | 0x30 | 0x01);
+
+    check("pixel_fill", rom.finish(), 32, 24, 0);
+}
+
+#[test]
+fn screen_vector_frames() {
+    let mut rom = Rom::new();
+
+    rom.deo2(0x2a, 4);
+    rom.deo2(0x20, 0x0200);
+
+    // every frame: increment the counter at 0x00 and plot a pixel there
+    rom.data(0x0200, &[
+        LIT, 0x00, 0x10, // LDZ
+        0x01, // INC
+        0x06, // DUP
+        LIT, 0x00, 0x11, // STZ
+        LIT, 0x29, DEO,
+        LIT, 0x03, LIT, 0x2e, DEO,
+        BRK,
+    ]);
+
+    check("screen_vector_frames", rom.finish(), 16, 8, 10);
+}
@@ -0,0 +1,28 @@
+// addresses as labels, and nothing made up where there is no label
+
+use auxn::symbols::SymbolTable;
+
+fn table() -> SymbolTable {
+    let entries = [(0x0100, String::from("on-reset")), (0x0120, String::from("routine/loop"))];
+
+    return SymbolTable::from_entries(&entries);
+}
+
+#[test]
+fn describe() {
+    let symbols = table();
+
+    assert_eq!(symbols.describe(0x0100).as_deref(), Some("on-reset"));
+    assert_eq!(symbols.describe(0x0124).as_deref(), Some("routine/loop+4"));
+    assert_eq!(symbols.describe(0x0080), None);
+    assert_eq!(symbols.describe(0x0300), None);
+}
+
+#[test]
+fn address_only_shows_a_label_when_there_is_one() {
+    let symbols = table();
+
+    assert_eq!(symbols.address(0x0124), "0124 routine/loop+4");
+    assert_eq!(symbols.address(0x0080), "0080");
+    assert_eq!(SymbolTable::new().address(0x0100), "0100");
+}
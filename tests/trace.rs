@@ -0,0 +1,125 @@
+// trace filters and the trace diff, on records made up by hand
+
+use auxn::symbols::SymbolTable;
+use auxn::trace::{self, Filter, Record};
+
+fn record(pc: u16, opcode: u8) -> Record {
+    Record {
+        pc,
+        opcode,
+        vector: 0x0100,
+
+        wst_ptr: 0,
+        rst_ptr: 0,
+        wst_top: 0,
+        rst_top: 0,
+        stacks: None,
+    }
+}
+
+// a run through pc 0100, 0101.. with one more byte on the working stack every step
+fn run(len: u16) -> Vec<Record> {
+    return (0..len)
+        .map(|i| Record {
+            wst_ptr: i as u8,
+            wst_top: i,
+            ..record(0x0100 + i, 0x01)
+        })
+        .collect();
+}
+
+fn filter(args: &[&str]) -> Filter {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+    return Filter::parse(&args).unwrap();
+}
+
+#[test]
+fn opcode_filter_ignores_mode_bits() {
+    let deo = filter(&["--op", "DEO"]);
+
+    assert!(deo.matches(&record(0x0100, 0x17)));
+    assert!(deo.matches(&record(0x0100, 0x37)));
+    assert!(deo.matches(&record(0x0100, 0x57)));
+    assert!(!deo.matches(&record(0x0100, 0x16)));
+}
+
+#[test]
+fn opcode_filter_tells_brk_from_lit() {
+    let brk = filter(&["--op", "BRK"]);
+    let lit = filter(&["--op", "LIT"]);
+
+    assert!(brk.matches(&record(0x0100, 0x00)));
+    assert!(!lit.matches(&record(0x0100, 0x00)));
+
+    for op in [0x80, 0xa0, 0xc0, 0xe0] {
+        assert!(lit.matches(&record(0x0100, op)));
+        assert!(!brk.matches(&record(0x0100, op)));
+    }
+}
+
+#[test]
+fn range_filter() {
+    let range = filter(&["--range", "0100-01ff"]);
+
+    assert!(range.matches(&record(0x0100, 0x01)));
+    assert!(range.matches(&record(0x01ff, 0x01)));
+    assert!(!range.matches(&record(0x0200, 0x01)));
+}
+
+#[test]
+fn identical_traces() {
+    let (a, b) = (run(20), run(20));
+
+    assert_eq!(trace::first_divergence(&a, &b), None);
+    assert_eq!(trace::diff_report(&a, &b, trace::DIFF_CONTEXT, &SymbolTable::new()), None);
+}
+
+#[test]
+fn divergence_in_the_middle() {
+    let a = run(20);
+    let mut b = run(20);
+    b[12].wst_top = 0xbeef;
+
+    assert_eq!(trace::first_divergence(&a, &b), Some(12));
+
+    let report = trace::diff_report(&a, &b, 2, &SymbolTable::new()).unwrap();
+    assert!(report.starts_with("traces diverge at instruction 12\n"));
+    assert!(report.contains("differs in wst\n"));
+    // two instructions before, and two after for each trace
+    assert!(report.contains("      10  010a"));
+    assert!(!report.contains("       9  0109"));
+    assert!(report.contains("a         14  010e"));
+    assert!(report.contains("b         14  010e"));
+}
+
+#[test]
+fn one_trace_ends_early() {
+    let (a, b) = (run(20), run(15));
+
+    assert_eq!(trace::first_divergence(&a, &b), Some(15));
+    assert_eq!(trace::first_divergence(&b, &a), Some(15));
+
+    let report = trace::diff_report(&a, &b, 2, &SymbolTable::new()).unwrap();
+    assert!(report.contains("b         15  (trace ends)"));
+}
+
+#[test]
+fn whole_stacks_are_compared_when_recorded() {
+    let with_stacks = |wst: Vec<u8>| Record {
+        wst_ptr: 2,
+        wst_top: 0x0203,
+        stacks: Some((wst, Vec::new())),
+        ..record(0x0100, 0x01)
+    };
+
+    // same pointer and top, something different further down
+    let a = vec![with_stacks(vec![0x02, 0x03]), record(0x0101, 0x01)];
+    let b = vec![with_stacks(vec![0x07, 0x03]), record(0x0101, 0x01)];
+
+    assert_eq!(trace::first_divergence(&a, &b), Some(0));
+
+    let report = trace::diff_report(&a, &b, 2, &SymbolTable::new()).unwrap();
+    assert!(report.contains("a   wst [02 03] rst []\n"));
+    assert!(report.contains("b   wst [07 03] rst []\n"));
+}